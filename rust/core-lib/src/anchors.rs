@@ -0,0 +1,198 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Items that plugins place at offsets in a view, such as inlay hints and
+//! decoration cursors, which core keeps in place as the text is edited.
+
+use serde_json::Value;
+
+use xi_rope::{RopeDelta, Transformer};
+
+use crate::client::Client;
+use crate::plugins::rpc::{DecorationCursor, InlayHint, InlayHintPosition};
+use crate::plugins::PluginId;
+use crate::tabs::ViewId;
+
+/// An item placed by a plugin at an offset in a view.
+pub trait Anchored {
+    fn offset(&self) -> usize;
+
+    fn set_offset(&mut self, offset: usize);
+
+    /// Whether text inserted at the item's offset goes before the item.
+    fn after_insert(&self) -> bool;
+
+    /// Encodes the item for the frontend, at the given line and column.
+    fn to_json(&self, line: usize, col: usize) -> Value;
+
+    /// Sends the frontend all the items of this kind that the plugin named
+    /// `source` has placed in a view.
+    fn send(client: &Client, view_id: ViewId, source: &str, items: &[Value]);
+}
+
+/// Moves `items` by `delta`.
+pub fn transform<T: Anchored>(items: &mut [T], delta: &RopeDelta) {
+    let mut transformer = Transformer::new(delta);
+    for item in items {
+        let offset = transformer.transform(item.offset(), item.after_insert());
+        item.set_offset(offset);
+    }
+}
+
+/// The items of one kind that plugins have placed in a view.
+pub struct AnchorStore<T> {
+    /// For each plugin that has placed items: its id, its name, and its items.
+    store: Vec<(PluginId, String, Vec<T>)>,
+}
+
+impl<T: Anchored> AnchorStore<T> {
+    pub fn new() -> Self {
+        AnchorStore { store: Vec::new() }
+    }
+
+    /// Returns the items placed by `plugin`, named `name`, for updating.
+    pub fn items_mut(&mut self, plugin: PluginId, name: &str) -> &mut Vec<T> {
+        let idx = match self.store.iter().position(|(p, _, _)| *p == plugin) {
+            Some(idx) => idx,
+            None => {
+                self.store.push((plugin, name.to_owned(), Vec::new()));
+                self.store.len() - 1
+            }
+        };
+        &mut self.store[idx].2
+    }
+
+    /// Returns the name of `plugin`, and the items it has placed.
+    pub fn get(&self, plugin: PluginId) -> Option<(&str, &[T])> {
+        self.store
+            .iter()
+            .find(|(p, _, _)| *p == plugin)
+            .map(|(_, name, items)| (name.as_str(), items.as_slice()))
+    }
+
+    /// Removes the items placed by `plugin`, returning whether there were
+    /// any.
+    pub fn clear(&mut self, plugin: PluginId) -> bool {
+        let had_items = self.get(plugin).map_or(false, |(_, items)| !items.is_empty());
+        self.store.retain(|(p, _, _)| *p != plugin);
+        had_items
+    }
+
+    /// Moves the items with an edit. Returns the plugins with items at or
+    /// after the start of the edit, whose lines or columns may have changed.
+    pub fn apply_delta(&mut self, delta: &RopeDelta) -> Vec<PluginId> {
+        let (iv, _) = delta.summary();
+        let mut moved = Vec::new();
+        for (plugin, _, items) in &mut self.store {
+            if items.iter().any(|item| item.offset() >= iv.start()) {
+                moved.push(*plugin);
+            }
+            transform(items, delta);
+        }
+        moved
+    }
+}
+
+impl Anchored for InlayHint {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    fn after_insert(&self) -> bool {
+        // a hint before its offset sticks to the text that follows it
+        self.position == InlayHintPosition::Before
+    }
+
+    fn to_json(&self, line: usize, col: usize) -> Value {
+        json!({
+            "line": line,
+            "col": col,
+            "label": self.label,
+            "position": self.position,
+            "padding": self.padding,
+        })
+    }
+
+    fn send(client: &Client, view_id: ViewId, source: &str, items: &[Value]) {
+        client.set_inlay_hints(view_id, source, items)
+    }
+}
+
+impl Anchored for DecorationCursor {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    fn after_insert(&self) -> bool {
+        false
+    }
+
+    fn to_json(&self, line: usize, col: usize) -> Value {
+        json!({
+            "id": self.id,
+            "line": line,
+            "col": col,
+            "color": self.color,
+        })
+    }
+
+    fn send(client: &Client, view_id: ViewId, source: &str, items: &[Value]) {
+        client.set_decoration_cursors(view_id, source, items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginPid;
+    use xi_rope::Interval;
+
+    fn hint(offset: usize, position: InlayHintPosition) -> InlayHint {
+        InlayHint { offset, label: ": u32".into(), position, padding: false }
+    }
+
+    #[test]
+    fn anchors_move_with_edits() {
+        let mut store = AnchorStore::new();
+        *store.items_mut(PluginPid(1), "one") =
+            vec![hint(5, InlayHintPosition::After), hint(12, InlayHintPosition::Before)];
+        *store.items_mut(PluginPid(2), "two") = vec![hint(2, InlayHintPosition::After)];
+
+        // text inserted at a hint's offset goes on the side away from it
+        let delta = RopeDelta::simple_edit(Interval::new(5, 5), "xyz".into(), 20);
+        assert_eq!(store.apply_delta(&delta), vec![PluginPid(1)]);
+        let (name, hints) = store.get(PluginPid(1)).unwrap();
+        assert_eq!(name, "one");
+        assert_eq!(hints.iter().map(|h| h.offset).collect::<Vec<_>>(), vec![5, 15]);
+
+        let delta = RopeDelta::simple_edit(Interval::new(12, 12), "2".into(), 23);
+        store.apply_delta(&delta);
+        assert_eq!(store.get(PluginPid(1)).unwrap().1[1].offset, 16);
+
+        assert!(store.clear(PluginPid(1)));
+        assert!(store.get(PluginPid(1)).is_none());
+        assert!(!store.clear(PluginPid(1)));
+        store.items_mut(PluginPid(3), "three");
+        assert!(!store.clear(PluginPid(3)));
+        assert_eq!(store.get(PluginPid(2)).unwrap().1[0].offset, 2);
+    }
+}
@@ -214,9 +214,10 @@ impl AnnotationStore {
         })
     }
 
-    /// Removes any annotations provided by this plugin
-    pub fn clear(&mut self, plugin: PluginId) {
-        self.store.remove(&plugin);
+    /// Removes any annotations provided by this plugin, returning whether
+    /// there were any.
+    pub fn clear(&mut self, plugin: PluginId) -> bool {
+        self.store.remove(&plugin).is_some()
     }
 }
 
@@ -14,13 +14,14 @@
 
 //! Requests and notifications from the core to front-ends.
 
+use std::path::PathBuf;
 use std::time::Instant;
 
 use serde_json::{self, Value};
 use xi_rpc::{self, RpcPeer};
 
 use crate::config::Table;
-use crate::plugins::rpc::ClientPluginInfo;
+use crate::plugins::rpc::{ClientPluginInfo, PeekContent};
 use crate::plugins::Command;
 use crate::styles::ThemeSettings;
 use crate::syntax::LanguageId;
@@ -228,6 +229,113 @@ impl Client {
         )
     }
 
+    pub fn show_prompt(
+        &self,
+        view_id: ViewId,
+        request_id: usize,
+        title: &str,
+        default: Option<&str>,
+    ) {
+        self.0.send_rpc_notification(
+            "show_prompt",
+            &json!({
+                "view_id": view_id,
+                "request_id": request_id,
+                "title": title,
+                "default": default,
+            }),
+        )
+    }
+
+    /// Dismisses a prompt shown with `show_prompt` that will not be answered,
+    /// because the plugin that asked for it has stopped.
+    pub fn hide_prompt(&self, view_id: ViewId, request_id: usize) {
+        self.0.send_rpc_notification(
+            "hide_prompt",
+            &json!({
+                "view_id": view_id,
+                "request_id": request_id,
+            }),
+        )
+    }
+
+    pub fn show_peek(&self, view_id: ViewId, line: usize, col: usize, content: &PeekContent) {
+        self.0.send_rpc_notification(
+            "show_peek",
+            &json!({
+                "view_id": view_id,
+                "line": line,
+                "col": col,
+                "content": content,
+            }),
+        )
+    }
+
+    pub fn hide_peek(&self, view_id: ViewId) {
+        self.0.send_rpc_notification("hide_peek", &json!({ "view_id": view_id }))
+    }
+
+    pub fn set_inlay_hints(&self, view_id: ViewId, source: &str, hints: &[Value]) {
+        self.0.send_rpc_notification(
+            "set_inlay_hints",
+            &json!({
+                "view_id": view_id,
+                "source": source,
+                "hints": hints,
+            }),
+        )
+    }
+
+    pub fn set_decoration_cursors(&self, view_id: ViewId, source: &str, cursors: &[Value]) {
+        self.0.send_rpc_notification(
+            "set_decoration_cursors",
+            &json!({
+                "view_id": view_id,
+                "source": source,
+                "cursors": cursors,
+            }),
+        )
+    }
+
+    pub fn task_started(&self, view_id: ViewId, task_id: usize, title: &str) {
+        self.0.send_rpc_notification(
+            "task_started",
+            &json!({
+                "view_id": view_id,
+                "task_id": task_id,
+                "title": title,
+            }),
+        )
+    }
+
+    pub fn task_progress(&self, view_id: ViewId, task_id: usize, message: &str, fraction: f64) {
+        self.0.send_rpc_notification(
+            "task_progress",
+            &json!({
+                "view_id": view_id,
+                "task_id": task_id,
+                "message": message,
+                "fraction": fraction,
+            }),
+        )
+    }
+
+    pub fn task_finished(&self, view_id: ViewId, task_id: usize) {
+        self.0.send_rpc_notification(
+            "task_finished",
+            &json!({
+                "view_id": view_id,
+                "task_id": task_id,
+            }),
+        )
+    }
+
+    /// Asks the frontend to open files that no plugin consumed when they
+    /// were dropped onto a view.
+    pub fn open_files(&self, paths: &[PathBuf]) {
+        self.0.send_rpc_notification("open_files", &json!({ "paths": paths }))
+    }
+
     pub fn schedule_idle(&self, token: usize) {
         self.0.schedule_idle(token)
     }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::io;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
 use serde_json::Value;
@@ -163,6 +164,21 @@ impl WeakXiCore {
             core.inner().plugin_update(plugin, view, response);
         }
     }
+
+    /// Handles a plugin's response to a `files_dropped` request, offering
+    /// the drop to the `remaining` plugins if it was not consumed.
+    pub fn handle_files_dropped(
+        &self,
+        view: ViewId,
+        paths: Vec<PathBuf>,
+        offset: usize,
+        remaining: Vec<PluginId>,
+        response: Result<Value, RpcError>,
+    ) {
+        if let Some(core) = self.upgrade() {
+            core.inner().files_dropped(view, paths, offset, remaining, response);
+        }
+    }
 }
 
 /// Handler for messages originating from plugins.
@@ -85,6 +85,9 @@ pub(crate) enum SpecialEvent {
     Resize(Size),
     RequestLines(LineRange),
     RequestHover { request_id: usize, position: Option<Position> },
+    PromptResponse { request_id: usize, result: Option<String> },
+    CancelTask { task_id: usize },
+    FilesDropped { paths: Vec<PathBuf>, position: Option<Position> },
     DebugToggleComment,
     Reindent,
     ToggleRecording(Option<String>),
@@ -290,6 +293,11 @@ impl From<EditNotification> for EventDomain {
             SelectionForReplace => ViewEvent::SelectionForReplace.into(),
             RequestHover { request_id, position } =>
                 SpecialEvent::RequestHover { request_id, position }.into(),
+            PromptResponse { request_id, result } =>
+                SpecialEvent::PromptResponse { request_id, result }.into(),
+            CancelTask { task_id } => SpecialEvent::CancelTask { task_id }.into(),
+            FilesDropped { paths, position } =>
+                SpecialEvent::FilesDropped { paths, position }.into(),
             SelectionIntoLines => ViewEvent::SelectionIntoLines.into(),
             DuplicateLine => BufferEvent::DuplicateLine.into(),
             IncreaseNumber => BufferEvent::IncreaseNumber.into(),
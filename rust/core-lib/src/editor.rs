@@ -14,7 +14,8 @@
 
 use std::borrow::{Borrow, Cow};
 use std::cmp::min;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, VecDeque};
+use std::mem;
 
 use serde_json::Value;
 
@@ -22,14 +23,18 @@ use xi_rope::diff::{Diff, LineHashDiff};
 use xi_rope::engine::{Engine, RevId, RevToken};
 use xi_rope::rope::count_newlines;
 use xi_rope::spans::SpansBuilder;
-use xi_rope::{Cursor, DeltaBuilder, Interval, LinesMetric, Rope, RopeDelta, Transformer};
+use xi_rope::{
+    Cursor, DeltaBuilder, DeltaElement, Interval, LinesMetric, Rope, RopeDelta, Transformer,
+};
 use xi_trace::{trace_block, trace_payload};
 
+use crate::anchors::{self, Anchored};
 use crate::annotations::{AnnotationType, Annotations};
 use crate::config::BufferItems;
 use crate::edit_types::BufferEvent;
 use crate::event_context::MAX_SIZE_LIMIT;
 use crate::layers::Layers;
+use crate::marks::Marks;
 use crate::movement::{region_movement, Movement};
 use crate::plugins::rpc::{DataSpan, GetDataResponse, PluginEdit, ScopeSpan, TextUnit};
 use crate::plugins::PluginId;
@@ -49,6 +54,20 @@ use fuchsia::sync::SyncStore;
 // better to keep it low to expose bugs in the GC during casual testing.
 const MAX_UNDOS: usize = 20;
 
+/// The priority of edits made by the user.
+const USER_EDIT_PRIORITY: u64 = 0x10000;
+
+/// Returns the priority the engine gives an edit of `priority` from a
+/// plugin with the manifest priority `plugin_priority`. Of two concurrent
+/// inserts at the same place, the one with the higher priority goes after
+/// the other; when their edits' priorities are equal, the higher manifest
+/// priority does.
+pub(crate) fn engine_priority(priority: u64, plugin_priority: i32) -> u64 {
+    // shift the manifest priority to be unsigned, keeping its order
+    let rank = (i64::from(plugin_priority) - i64::from(i32::min_value())) as u64;
+    (priority.min(u64::from(u32::max_value())) << 32) | rank
+}
+
 enum IndentDirection {
     In,
     Out,
@@ -64,6 +83,8 @@ pub struct Editor {
     last_rev_id: RevId,
     /// The revision of the last save.
     pristine_rev_id: RevId,
+    /// Whether the buffer was pristine when plugins were last told.
+    reported_pristine: bool,
     undo_group_id: usize,
     /// Undo groups that may still be toggled
     live_undos: Vec<usize>,
@@ -88,6 +109,21 @@ pub struct Editor {
     last_synced_rev: RevId,
 
     layers: Layers,
+    /// Named marks set by plugins.
+    marks: Marks,
+
+    /// Chunks of chunked plugin edits, waiting to be applied.
+    pending_chunks: VecDeque<PendingChunk>,
+    /// The revision left by the last chunk applied.
+    last_chunk_rev: RevToken,
+}
+
+/// A piece of a chunked plugin edit, waiting to be applied.
+struct PendingChunk {
+    edit: PluginEdit,
+    /// Whether the chunk applies to the text left by the chunk before it,
+    /// rather than to revision `edit.rev`.
+    follows_previous: bool,
 }
 
 impl Editor {
@@ -107,6 +143,7 @@ impl Editor {
             engine,
             last_rev_id,
             pristine_rev_id: last_rev_id,
+            reported_pristine: true,
             undo_group_id: 1,
             // GC only works on undone edits or prefixes of the visible edits,
             // but initial file loading can create an edit with undo group 0,
@@ -119,9 +156,12 @@ impl Editor {
             last_edit_type: EditType::Other,
             this_edit_type: EditType::Other,
             layers: Layers::default(),
+            marks: Marks::default(),
             revs_in_flight: 0,
             sync_store: None,
             last_synced_rev: last_rev_id,
+            pending_chunks: VecDeque::new(),
+            last_chunk_rev: last_rev_id.token(),
         }
     }
 
@@ -137,6 +177,44 @@ impl Editor {
         &mut self.layers
     }
 
+    pub(crate) fn get_marks(&self) -> &Marks {
+        &self.marks
+    }
+
+    pub(crate) fn get_marks_mut(&mut self) -> &mut Marks {
+        &mut self.marks
+    }
+
+    /// Removes and returns the buffer's named marks, to be kept after it
+    /// closes. Markers are dropped.
+    pub(crate) fn take_marks(&mut self) -> Marks {
+        let mut marks = mem::replace(&mut self.marks, Marks::default());
+        marks.clear_markers();
+        marks
+    }
+
+    /// Restores marks kept from an earlier buffer of the same file. Marks
+    /// past the end of the text are moved to its end.
+    pub(crate) fn restore_marks(&mut self, mut marks: Marks) {
+        marks.clamp(self.text.len());
+        self.marks = marks;
+    }
+
+    /// Returns where `offset` in revision `rev`, such as the offset of a
+    /// mark a plugin has set, is in the head revision.
+    pub(crate) fn offset_at_head(&self, offset: usize, rev: RevToken) -> Option<usize> {
+        match self.engine.try_delta_rev_head(rev) {
+            Ok(delta) => {
+                let offset = offset.min(delta.base_len);
+                Some(Transformer::new(&delta).transform(offset, false))
+            }
+            Err(_) => {
+                error!("Revision {} not found", rev);
+                None
+            }
+        }
+    }
+
     pub(crate) fn get_head_rev_token(&self) -> u64 {
         self.engine.get_head_rev_id().token()
     }
@@ -162,6 +240,17 @@ impl Editor {
         self.engine.is_equivalent_revision(self.pristine_rev_id, self.engine.get_head_rev_id())
     }
 
+    /// Returns the buffer's pristine state if it has changed since the last
+    /// call, so that plugins are only told about transitions.
+    pub(crate) fn take_pristine_change(&mut self) -> Option<bool> {
+        let pristine = self.is_pristine();
+        if pristine == self.reported_pristine {
+            return None;
+        }
+        self.reported_pristine = pristine;
+        Some(pristine)
+    }
+
     /// Set whether or not edits are forced into the same undo group rather than being split by
     /// their EditType.
     ///
@@ -235,7 +324,7 @@ impl Editor {
         let head_rev_id = self.engine.get_head_rev_id();
         let undo_group = self.calculate_undo_group();
         self.last_edit_type = self.this_edit_type;
-        let priority = 0x10000;
+        let priority = engine_priority(USER_EDIT_PRIORITY, 0) as usize;
         self.engine.edit_rev(priority, undo_group, head_rev_id.token(), delta);
         self.text = self.engine.get_head().clone();
     }
@@ -263,6 +352,8 @@ impl Editor {
     }
 
     /// generates a delta from a plugin's response and applies it to the buffer.
+    /// The edit's priority is the one given to the engine, as returned by
+    /// `engine_priority`.
     pub fn apply_plugin_edit(&mut self, edit: PluginEdit) {
         let _t = trace_block("Editor::apply_plugin_edit", &["core"]);
         //TODO: get priority working, so that plugin edits don't necessarily move cursor
@@ -275,6 +366,145 @@ impl Editor {
         };
     }
 
+    /// Applies a plugin edit made against an earlier revision, like
+    /// `apply_plugin_edit`, but only if none of the changes made since that
+    /// revision overlap the regions it modifies. Returns `false`, leaving
+    /// the buffer unchanged, if one does or if the revision is unknown.
+    pub(crate) fn apply_rebased_edit(&mut self, edit: PluginEdit) -> bool {
+        let intervening = match self.engine.try_delta_rev_head(edit.rev) {
+            Ok(delta) => delta,
+            Err(_) => return false,
+        };
+        if intervening.base_len != edit.delta.base_len {
+            return false;
+        }
+        let theirs = changed_regions(&intervening);
+        let conflict = changed_regions(&edit.delta)
+            .iter()
+            .any(|ours| theirs.iter().any(|iv| ours.start < iv.end && iv.start < ours.end));
+        if conflict {
+            return false;
+        }
+        self.apply_plugin_edit(edit);
+        true
+    }
+
+    /// Splits a plugin edit into a series of smaller edits in one undo
+    /// group, each deleting and inserting at most `chunk_size` bytes, and
+    /// queues them to be applied one at a time by `apply_next_chunk`.
+    pub(crate) fn queue_chunked_edit(&mut self, edit: PluginEdit, chunk_size: usize) {
+        let base = match self.engine.get_rev(edit.rev) {
+            Some(ref base) if base.len() == edit.delta.base_len => base.clone(),
+            // a bad revision or delta is reported when the edit is applied
+            _ => {
+                self.pending_chunks.push_back(PendingChunk { edit, follows_previous: false });
+                return;
+            }
+        };
+        let undo_group = Some(edit.undo_group.unwrap_or_else(|| self.calculate_undo_group()));
+        for (i, delta) in split_delta(&base, &edit.delta, chunk_size).into_iter().enumerate() {
+            let chunk = PluginEdit {
+                rev: edit.rev,
+                delta,
+                priority: edit.priority,
+                after_cursor: edit.after_cursor,
+                undo_group,
+                author: edit.author.clone(),
+                auto_pair: false,
+                chunk_size: None,
+            };
+            self.pending_chunks.push_back(PendingChunk { edit: chunk, follows_previous: i > 0 });
+        }
+    }
+
+    /// Applies the next queued chunk of a chunked plugin edit, returning its
+    /// author, or `None` if there are no chunks left.
+    ///
+    /// Each chunk is applied at the revision left by the one before it, so
+    /// it is only rebased over edits made in between.
+    pub(crate) fn apply_next_chunk(&mut self) -> Option<String> {
+        let PendingChunk { mut edit, follows_previous } = self.pending_chunks.pop_front()?;
+        if follows_previous {
+            edit.rev = self.last_chunk_rev;
+        }
+        let author = edit.author.clone();
+        let prev_rev = self.get_head_rev_token();
+        self.apply_plugin_edit(edit);
+        self.last_chunk_rev = self.get_head_rev_token();
+        if self.last_chunk_rev == prev_rev {
+            // the rest of the edit relies on this chunk, so it can't apply either
+            while self.pending_chunks.front().map_or(false, |chunk| chunk.follows_previous) {
+                self.pending_chunks.pop_front();
+            }
+        }
+        Some(author)
+    }
+
+    /// Returns `true` if there are chunks of plugin edits left to apply.
+    pub(crate) fn has_pending_chunks(&self) -> bool {
+        !self.pending_chunks.is_empty()
+    }
+
+    /// Applies a plugin edit as `apply_plugin_edit` does, first adding the
+    /// closing half of each surrounding pair whose opening half the edit
+    /// inserts. Returns, for each pair completed this way, the offset
+    /// between its halves and the length of its closing half. Pairs the
+    /// edit inserts whole are left alone.
+    pub(crate) fn apply_plugin_edit_paired(
+        &mut self,
+        mut edit: PluginEdit,
+        pairs: &[(String, String)],
+    ) -> Vec<(usize, usize)> {
+        // the length of each insert, and of the closing half added to it
+        let mut inserts = Vec::new();
+        for el in edit.delta.els.iter_mut() {
+            if let DeltaElement::Insert(ref mut rope) = *el {
+                let inserted = String::from(&*rope);
+                let closing = pairs.iter().find(|pair| pair.0 == inserted).map(|pair| &pair.1);
+                if let Some(closing) = closing {
+                    *rope = Rope::from(format!("{}{}", inserted, closing));
+                }
+                if !rope.is_empty() {
+                    inserts.push((rope.len(), closing.map(String::len)));
+                }
+            }
+        }
+
+        let prev_rev = self.get_head_rev_token();
+        self.apply_plugin_edit(edit);
+
+        // The edit may have been rebased, so find its inserts in the applied
+        // delta. They keep their order, but inserts left next to each other
+        // are merged into one.
+        let delta = match self.delta_rev_head(prev_rev) {
+            Some(delta) => delta,
+            None => return Vec::new(),
+        };
+        let mut inserts = inserts.into_iter();
+        let mut result = Vec::new();
+        let mut offset = 0;
+        for el in &delta.els {
+            match *el {
+                DeltaElement::Copy(beg, end) => offset += end - beg,
+                DeltaElement::Insert(ref rope) => {
+                    let end = offset + rope.len();
+                    while offset < end {
+                        let (len, closing_len) = match inserts.next() {
+                            Some(insert) => insert,
+                            None => break,
+                        };
+                        if let Some(closing_len) = closing_len {
+                            result.push((offset + len - closing_len, closing_len));
+                        }
+                        offset += len;
+                    }
+                    offset = end;
+                }
+            }
+        }
+        result
+    }
+
     /// Commits the current delta. If the buffer has changed, returns
     /// a 3-tuple containing the delta representing the changes, the previous
     /// buffer, and an `InsertDrift` enum describing the correct selection update
@@ -300,6 +530,7 @@ impl Editor {
             _ => InsertDrift::Default,
         };
         self.layers.update_all(&delta);
+        self.marks.apply_delta(&delta);
 
         self.last_rev_id = self.engine.get_head_rev_id();
         self.sync_state_changed();
@@ -787,36 +1018,55 @@ impl Editor {
     }
 
     fn duplicate_line(&mut self, view: &View, config: &BufferItems) {
-        let mut builder = DeltaBuilder::new(self.text.len());
-        // get affected lines or regions
-        let mut to_duplicate = BTreeSet::new();
-
-        for region in view.sel_regions() {
-            let (first_line, _) = view.offset_to_line_col(&self.text, region.min());
-            let line_start = view.offset_of_line(&self.text, first_line);
-
-            let mut cursor = match region.is_caret() {
-                true => Cursor::new(&self.text, line_start),
-                false => {
-                    // duplicate all lines together that are part of the same selections
-                    let (last_line, _) = view.offset_to_line_col(&self.text, region.max());
-                    let line_end = view.offset_of_line(&self.text, last_line);
-                    Cursor::new(&self.text, line_end)
-                }
-            };
-
-            if let Some(line_end) = cursor.next::<LinesMetric>() {
-                to_duplicate.insert((line_start, line_end));
-            }
+        // duplicate all lines together that are part of the same selections
+        let to_duplicate =
+            view.sel_regions().iter().map(|region| self.line_span(view, region)).collect();
+        self.insert_duplicates(to_duplicate, config);
+    }
+
+    /// Duplicates each selection in a single edit: selected text is copied
+    /// immediately after itself, and the line containing a caret is copied
+    /// below it.
+    pub(crate) fn duplicate_selection(&mut self, view: &View, config: &BufferItems) {
+        let to_duplicate = view
+            .sel_regions()
+            .iter()
+            .map(|region| match region.is_caret() {
+                true => self.line_span(view, region),
+                false => (region.min(), region.max(), false),
+            })
+            .collect();
+        self.insert_duplicates(to_duplicate, config);
+    }
+
+    /// Returns the start and end of the lines `region` touches, and whether
+    /// the last of them is missing a line ending.
+    fn line_span(&self, view: &View, region: &SelRegion) -> (usize, usize, bool) {
+        let (first_line, _) = view.offset_to_line_col(&self.text, region.min());
+        let line_start = view.offset_of_line(&self.text, first_line);
+        let (last_line, _) = view.offset_to_line_col(&self.text, region.max());
+        let last_line_start = view.offset_of_line(&self.text, last_line);
+        match Cursor::new(&self.text, last_line_start).next::<LinesMetric>() {
+            Some(line_end) => (line_start, line_end, false),
+            None => (line_start, self.text.len(), true),
         }
+    }
 
-        for (start, end) in to_duplicate {
-            // insert duplicates
+    /// Inserts a copy of each `(start, end, needs_line_ending)` range in
+    /// front of the original, in a single edit. Copies of ranges that need
+    /// a line ending, because the last line has none, get one added.
+    fn insert_duplicates(
+        &mut self,
+        to_duplicate: BTreeSet<(usize, usize, bool)>,
+        config: &BufferItems,
+    ) {
+        let mut builder = DeltaBuilder::new(self.text.len());
+        // The copy goes in front of the original, which puts the selection
+        // on the copy that follows; the two are identical.
+        for (start, end, needs_line_ending) in to_duplicate {
             let iv = Interval::new(start, start);
             builder.replace(iv, self.text.slice(start..end));
-
-            // last line does not have new line character so it needs to be manually added
-            if end == self.text.len() {
+            if needs_line_ending {
                 builder.replace(iv, Rope::from(&config.line_ending))
             }
         }
@@ -937,6 +1187,18 @@ impl Editor {
         view.update_annotations(plugin, iv, Annotations { items: spans, annotation_type });
     }
 
+    /// Moves items a plugin placed in revision `rev` to where they are in
+    /// the head revision.
+    pub(crate) fn transform_to_head<T: Anchored>(&self, rev: RevToken, items: &mut [T]) {
+        if rev == self.engine.get_head_rev_id().token() {
+            return;
+        }
+        match self.engine.try_delta_rev_head(rev) {
+            Ok(delta) => anchors::transform(items, &delta),
+            Err(_) => error!("Revision {} not found", rev),
+        }
+    }
+
     pub(crate) fn get_rev(&self, rev: RevToken) -> Option<Cow<Rope>> {
         let text_cow = if rev == self.engine.get_head_rev_id().token() {
             Cow::Borrowed(&self.text)
@@ -1022,6 +1284,91 @@ fn n_spaces(n: usize) -> &'static str {
     &spaces[..n]
 }
 
+/// Returns the regions of `delta`'s base text that it deletes or replaces,
+/// in order. Insertions are represented by empty intervals, and so only
+/// overlap the regions which strictly contain them.
+fn changed_regions(delta: &RopeDelta) -> Vec<Interval> {
+    let mut regions = Vec::new();
+    let mut pos = 0;
+    for el in &delta.els {
+        match *el {
+            DeltaElement::Copy(start, end) => {
+                if start > pos {
+                    regions.push(Interval::new(pos, start));
+                }
+                pos = end;
+            }
+            DeltaElement::Insert(_) => regions.push(Interval::new(pos, pos)),
+        }
+    }
+    if delta.base_len > pos {
+        regions.push(Interval::new(pos, delta.base_len));
+    }
+    regions
+}
+
+/// Splits `delta` into deltas that each delete and insert at most
+/// `chunk_size` bytes, rounded up to a codepoint boundary. The first piece
+/// applies to `base`, and each of the others to the text left by the one
+/// before it; applying them all in order gives the same text as `delta`.
+fn split_delta(base: &Rope, delta: &RopeDelta, chunk_size: usize) -> Vec<RopeDelta> {
+    let chunk_size = chunk_size.max(1);
+
+    // collect the regions of `base` that are replaced, and their new text
+    let mut replacements = Vec::new();
+    let mut pos = 0;
+    let mut inserted = String::new();
+    for el in &delta.els {
+        match *el {
+            DeltaElement::Copy(beg, end) => {
+                if beg > pos || !inserted.is_empty() {
+                    replacements.push((
+                        Interval::new(pos, beg),
+                        mem::replace(&mut inserted, String::new()),
+                    ));
+                }
+                pos = end;
+            }
+            DeltaElement::Insert(ref rope) => inserted.push_str(&String::from(rope)),
+        }
+    }
+    if delta.base_len > pos || !inserted.is_empty() {
+        replacements.push((Interval::new(pos, delta.base_len), inserted));
+    }
+
+    let mut result = Vec::new();
+    // the bytes the pieces so far have deleted and inserted
+    let mut deleted = 0;
+    let mut inserted = 0;
+    for (iv, text) in replacements {
+        let mut del_start = iv.start();
+        let mut ins_start = 0;
+        loop {
+            let del_end = min(del_start + chunk_size, iv.end());
+            let del_end = base.at_or_next_codepoint_boundary(del_end).unwrap_or(iv.end());
+            let mut ins_end = min(ins_start + chunk_size, text.len());
+            while !text.is_char_boundary(ins_end) {
+                ins_end += 1;
+            }
+            // where the region is in the text left by the previous piece
+            let start = del_start - deleted + inserted;
+            let end = start + (del_end - del_start);
+            let mut builder = DeltaBuilder::new(base.len() - deleted + inserted);
+            builder.replace(start..end, Rope::from(&text[ins_start..ins_end]));
+            result.push(builder.build());
+
+            deleted += del_end - del_start;
+            inserted += ins_end - ins_start;
+            del_start = del_end;
+            ins_start = ins_end;
+            if del_start == iv.end() && ins_start == text.len() {
+                break;
+            }
+        }
+    }
+    result
+}
+
 /// Counts the number of lines in the string, not including any trailing newline.
 fn count_lines(s: &str) -> usize {
     let mut newlines = count_newlines(s);
@@ -1035,6 +1382,17 @@ fn count_lines(s: &str) -> usize {
 mod tests {
     use super::*;
 
+    #[test]
+    fn engine_priorities() {
+        // the edit's own priority comes first
+        assert!(engine_priority(2, i32::min_value()) > engine_priority(1, i32::max_value()));
+        assert!(engine_priority(1, 1) > engine_priority(1, 0));
+        assert!(engine_priority(1, 0) > engine_priority(1, -1));
+        assert!(engine_priority(1, i32::min_value()) > engine_priority(0, i32::max_value()));
+        let max = u64::from(u32::max_value());
+        assert!(engine_priority(u64::max_value(), 0) > engine_priority(max, -1));
+    }
+
     #[test]
     fn plugin_edit() {
         let base_text = "hello";
@@ -1051,6 +1409,8 @@ mod tests {
             after_cursor: false,
             undo_group: None,
             author: "plugin_one".into(),
+            auto_pair: false,
+            chunk_size: None,
         };
 
         editor.apply_plugin_edit(edit_one.clone());
@@ -1058,4 +1418,128 @@ mod tests {
 
         assert_eq!(editor.get_buffer().to_string(), "sshello");
     }
+
+    #[test]
+    fn pristine_changes() {
+        let base_text = "hello";
+        let mut editor = Editor::with_text(base_text);
+        assert_eq!(editor.take_pristine_change(), None);
+
+        let delta = RopeDelta::simple_edit(Interval::new(5, 5), " world".into(), 5);
+        editor.add_delta(delta);
+        assert_eq!(editor.take_pristine_change(), Some(false));
+        let delta = RopeDelta::simple_edit(Interval::new(11, 11), "!".into(), 11);
+        editor.add_delta(delta);
+        assert_eq!(editor.take_pristine_change(), None);
+
+        editor.set_pristine();
+        assert_eq!(editor.take_pristine_change(), Some(true));
+        assert_eq!(editor.take_pristine_change(), None);
+    }
+
+    #[test]
+    fn rebased_plugin_edit() {
+        let base_text = "fn foo() {\n    bar()\n}\n";
+        let mut editor = Editor::with_text(base_text);
+        let base_rev = editor.get_head_rev_token();
+        let edit = |rev, iv: Interval, text: &str, len| PluginEdit {
+            rev,
+            delta: RopeDelta::simple_edit(iv, text.into(), len),
+            priority: 55,
+            after_cursor: false,
+            undo_group: None,
+            author: "plugin_one".into(),
+            auto_pair: false,
+            chunk_size: None,
+        };
+
+        // an intervening edit, after the formatter's region
+        editor.apply_plugin_edit(edit(base_rev, Interval::new(15, 20), "baz()", base_text.len()));
+        assert_eq!(editor.get_buffer().to_string(), "fn foo() {\n    baz()\n}\n");
+
+        let format = edit(base_rev, Interval::new(3, 6), "qux", base_text.len());
+        assert!(editor.apply_rebased_edit(format));
+        assert_eq!(editor.get_buffer().to_string(), "fn qux() {\n    baz()\n}\n");
+
+        // overlaps the intervening edit
+        let conflicting = edit(base_rev, Interval::new(11, 20), "bar();", base_text.len());
+        assert!(!editor.apply_rebased_edit(conflicting));
+        assert_eq!(editor.get_buffer().to_string(), "fn qux() {\n    baz()\n}\n");
+
+        // an insertion strictly inside a changed region also conflicts
+        let inserting = edit(base_rev, Interval::new(17, 17), "x", base_text.len());
+        assert!(!editor.apply_rebased_edit(inserting));
+
+        // the base length doesn't match the revision
+        let wrong_len = edit(base_rev, Interval::new(0, 0), "x", 3);
+        assert!(!editor.apply_rebased_edit(wrong_len));
+    }
+
+    #[test]
+    fn chunked_plugin_edit() {
+        let base_text = "hello w\u{f6}rld";
+        let mut editor = Editor::with_text(base_text);
+        let replacement = "\u{e9}".repeat(12);
+        let mut builder = DeltaBuilder::new(base_text.len());
+        builder.replace(6..base_text.len(), replacement.as_str().into());
+
+        let edit = PluginEdit {
+            rev: editor.get_head_rev_token(),
+            delta: builder.build(),
+            priority: 55,
+            after_cursor: false,
+            undo_group: None,
+            author: "plugin_one".into(),
+            auto_pair: false,
+            chunk_size: Some(5),
+        };
+
+        editor.queue_chunked_edit(edit, 5);
+        let mut chunks = 0;
+        while editor.apply_next_chunk().is_some() {
+            chunks += 1;
+            if chunks == 1 {
+                // the user edits while the chunks are being applied
+                let rev = editor.get_head_rev_token();
+                let len = editor.get_buffer().len();
+                let user_edit = PluginEdit {
+                    rev,
+                    delta: RopeDelta::simple_edit(Interval::new(0, 0), "oh, ".into(), len),
+                    priority: 0,
+                    after_cursor: false,
+                    undo_group: None,
+                    author: "core".into(),
+                    auto_pair: false,
+                    chunk_size: None,
+                };
+                editor.apply_plugin_edit(user_edit);
+            }
+        }
+        assert_eq!(chunks, 4);
+        assert!(!editor.has_pending_chunks());
+        assert_eq!(editor.get_buffer().to_string(), format!("oh, hello {}", replacement));
+
+        // the user's edit is undone first, then all the chunks together
+        editor.do_undo();
+        assert_eq!(editor.get_buffer().to_string(), format!("hello {}", replacement));
+        editor.do_undo();
+        assert_eq!(editor.get_buffer().to_string(), base_text);
+    }
+
+    #[test]
+    fn split_delta_pieces_follow_each_other() {
+        let base = Rope::from("abcdefgh");
+        let mut builder = DeltaBuilder::new(base.len());
+        builder.replace(1..3, "XYZ".into());
+        builder.delete(5..8);
+        let delta = builder.build();
+        let pieces = split_delta(&base, &delta, 2);
+        assert_eq!(pieces.len(), 4);
+        let mut text = base.clone();
+        for piece in &pieces {
+            assert_eq!(piece.base_len, text.len());
+            text = piece.apply(&text);
+        }
+        assert_eq!(String::from(text), String::from(delta.apply(&base)));
+    }
 }
@@ -17,7 +17,8 @@
 use std::cell::RefCell;
 use std::iter;
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::slice;
 use std::time::{Duration, Instant};
 
 use serde_json::{self, Value};
@@ -27,22 +28,24 @@ use xi_rpc::{Error as RpcError, RemoteError};
 use xi_trace::trace_block;
 
 use crate::plugins::rpc::{
-    ClientPluginInfo, Hover, PluginBufferInfo, PluginNotification, PluginRequest, PluginUpdate,
+    ClientPluginInfo, DecorationCursor, Hover, InlayHint, MarkerId, PluginBufferInfo, PluginEdit,
+    PluginNotification, PluginRequest, PluginUpdate,
 };
 use crate::rpc::{EditNotification, EditRequest, LineRange, Position as ClientPosition};
 
 use crate::client::Client;
 use crate::config::{BufferItems, Table};
 use crate::edit_types::{EventDomain, SpecialEvent};
-use crate::editor::Editor;
-use crate::file::FileInfo;
+use crate::editor::{self, Editor};
+use crate::file::{self, FileError, FileInfo};
 use crate::plugins::Plugin;
 use crate::recorder::Recorder;
-use crate::selection::InsertDrift;
+use crate::selection::{InsertDrift, SelRegion, Selection};
 use crate::styles::ThemeStyleMap;
 use crate::syntax::LanguageId;
 use crate::tabs::{
-    BufferId, PluginId, ViewId, FIND_VIEW_IDLE_MASK, RENDER_VIEW_IDLE_MASK, REWRAP_VIEW_IDLE_MASK,
+    BufferId, PluginId, ViewId, EDIT_CHUNK_VIEW_IDLE_MASK, FIND_VIEW_IDLE_MASK,
+    RENDER_VIEW_IDLE_MASK, REWRAP_VIEW_IDLE_MASK,
 };
 use crate::view::View;
 use crate::width_cache::WidthCache;
@@ -174,6 +177,16 @@ impl<'a> EventContext<'a> {
             SpecialEvent::RequestHover { request_id, position } => {
                 self.do_request_hover(request_id, position)
             }
+            SpecialEvent::PromptResponse { request_id, result } => {
+                self.do_prompt_response(request_id, result)
+            }
+            SpecialEvent::CancelTask { task_id } => self.do_cancel_task(task_id),
+            SpecialEvent::FilesDropped { paths, position } => {
+                if let Some(offset) = self.get_resolved_position(position) {
+                    let plugins = self.plugins.iter().map(|p| p.id).collect();
+                    self.do_files_dropped(paths, offset, plugins)
+                }
+            }
             SpecialEvent::DebugToggleComment => self.do_debug_toggle_comment(),
             SpecialEvent::Reindent => self.do_reindent(),
             SpecialEvent::ToggleRecording(_) => {}
@@ -226,6 +239,7 @@ impl<'a> EventContext<'a> {
 
     pub(crate) fn do_plugin_cmd(&mut self, plugin: PluginId, cmd: PluginNotification) {
         use self::PluginNotification::*;
+        let mut auto_closed = Vec::new();
         match cmd {
             AddScopes { scopes } => {
                 let mut ed = self.editor.borrow_mut();
@@ -235,11 +249,12 @@ impl<'a> EventContext<'a> {
             UpdateSpans { start, len, spans, rev } => self.with_editor(|ed, view, _, _| {
                 ed.update_spans(view, plugin, start, len, spans, rev)
             }),
-            Edit { edit } => self.with_editor(|ed, _, _, _| ed.apply_plugin_edit(edit)),
+            Edit { edit } => auto_closed = self.do_plugin_edit(plugin, edit),
             Alert { msg } => self.client.alert(&msg),
             AddStatusItem { key, value, alignment } => {
                 let plugin_name = &self.plugins.iter().find(|p| p.id == plugin).unwrap().name;
                 self.client.add_status_item(self.view_id, plugin_name, &key, &value, &alignment);
+                self.view.borrow_mut().add_status_item(plugin, &key);
             }
             UpdateStatusItem { key, value } => {
                 self.client.update_status_item(self.view_id, &key, &value)
@@ -249,22 +264,292 @@ impl<'a> EventContext<'a> {
                     ed.update_annotations(view, plugin, start, len, spans, annotation_type, rev)
                 })
             }
-            RemoveStatusItem { key } => self.client.remove_status_item(self.view_id, &key),
+            RemoveStatusItem { key } => {
+                self.view.borrow_mut().remove_status_item(plugin, &key);
+                self.client.remove_status_item(self.view_id, &key)
+            }
             ShowHover { request_id, result } => self.do_show_hover(request_id, result),
+            Prompt { request_id, title, default } => {
+                let id = self.view.borrow_mut().add_pending_prompt(plugin, request_id);
+                self.client.show_prompt(
+                    self.view_id,
+                    id,
+                    &title,
+                    default.as_ref().map(String::as_str),
+                )
+            }
+            ShowPeek { anchor, content } => {
+                let (line, col) =
+                    self.with_view(|view, text| view.offset_to_line_col(text, anchor));
+                self.client.show_peek(self.view_id, line, col, &content)
+            }
+            HidePeek => self.client.hide_peek(self.view_id),
+            BeginTask { task_id, title } => {
+                let id = self.view.borrow_mut().add_task(plugin, task_id);
+                self.client.task_started(self.view_id, id, &title)
+            }
+            UpdateTask { task_id, message, fraction } => {
+                match self.view.borrow().task_id(plugin, task_id) {
+                    Some(id) => self.client.task_progress(self.view_id, id, &message, fraction),
+                    None => warn!("progress for unknown task {} from {:?}", task_id, plugin),
+                }
+            }
+            FinishTask { task_id } => match self.view.borrow_mut().remove_task(plugin, task_id) {
+                Some(id) => self.client.task_finished(self.view_id, id),
+                None => warn!("finish for unknown task {} from {:?}", task_id, plugin),
+            },
+            SetInlayHints { hints, rev } => self.do_set_inlay_hints(plugin, hints, rev),
+            SetDecorationCursor { cursor, rev } => {
+                self.do_set_decoration_cursor(plugin, cursor, rev)
+            }
+            RemoveDecorationCursor { id } => self.do_remove_decoration_cursor(plugin, &id),
+            SetNamedMark { name, offset, rev } => {
+                self.do_set_named_mark(plugin, &name, offset, rev)
+            }
+            RemoveNamedMark { name } => self.do_remove_named_mark(plugin, &name),
+            SetMarker { id, offset, rev } => self.do_set_marker(plugin, id, offset, rev),
+            RemoveMarker { id } => {
+                self.editor.borrow_mut().get_marks_mut().remove_marker(plugin, id)
+            }
+            InsertAtMarker { marker, text, priority, author } => {
+                self.do_insert_at_marker(plugin, marker, text, priority, author)
+            }
+            ClearContributions => match self.plugins.iter().find(|p| p.id == plugin).cloned() {
+                Some(p) => self.clear_contributions(p.id, &p.name),
+                None => warn!("clear_contributions from unknown plugin {:?}", plugin),
+            },
+            DuplicateSelection => {
+                self.with_editor(|ed, view, _, config| ed.duplicate_selection(view, config))
+            }
         };
         self.after_edit(&plugin.to_string());
+        // after the commit, so that the carets have been moved past the edit
+        if !auto_closed.is_empty() {
+            self.move_carets_into_pairs(&auto_closed);
+        }
         self.render_if_needed();
     }
 
-    pub(crate) fn do_plugin_cmd_sync(&mut self, _plugin: PluginId, cmd: PluginRequest) -> Value {
+    pub(crate) fn do_plugin_cmd_sync(
+        &mut self,
+        plugin: PluginId,
+        cmd: PluginRequest,
+    ) -> Result<Value, RemoteError> {
         use self::PluginRequest::*;
         match cmd {
-            LineCount => json!(self.editor.borrow().plugin_n_lines()),
+            LineCount => Ok(json!(self.editor.borrow().plugin_n_lines())),
             GetData { start, unit, max_size, rev } => {
-                json!(self.editor.borrow().plugin_get_data(start, unit, max_size, rev))
+                Ok(json!(self.editor.borrow().plugin_get_data(start, unit, max_size, rev)))
+            }
+            GetSelections => Ok(json!(self.view.borrow().plugin_selections())),
+            GetSearchState => Ok(json!(self.view.borrow().search_state())),
+            RebasedEdit { edit } => self.do_rebased_edit(plugin, edit),
+            GetDiskContentHash => self.do_disk_content_hash(),
+        }
+    }
+
+    /// Returns a hash of the view's file as it is on disk, or `null` if
+    /// the view has no file or it doesn't exist yet.
+    fn do_disk_content_hash(&self) -> Result<Value, RemoteError> {
+        let path = match self.info {
+            Some(info) => &info.path,
+            None => return Ok(Value::Null),
+        };
+        match file::content_hash(path) {
+            Ok(hash) => Ok(json!(hash)),
+            Err(e) => Err(FileError::Io(e, path.to_owned()).into()),
+        }
+    }
+
+    /// Sets a plugin's inlay hints, placed at revision `rev`, replacing any
+    /// it set before. The view keeps them in place through later edits.
+    fn do_set_inlay_hints(&mut self, plugin: PluginId, mut hints: Vec<InlayHint>, rev: u64) {
+        let plugin_name = match self.plugins.iter().find(|p| p.id == plugin) {
+            Some(plugin) => plugin.name.clone(),
+            None => return,
+        };
+        let ed = self.editor.borrow();
+        ed.transform_to_head(rev, &mut hints);
+        let text = ed.get_buffer();
+        self.view.borrow_mut().set_inlay_hints(text, self.client, plugin, &plugin_name, hints);
+    }
+
+    /// Places a plugin's decoration cursor at revision `rev`, replacing its
+    /// cursor with the same id. The view keeps it in place through later
+    /// edits.
+    fn do_set_decoration_cursor(
+        &mut self,
+        plugin: PluginId,
+        mut cursor: DecorationCursor,
+        rev: u64,
+    ) {
+        let plugin_name = match self.plugins.iter().find(|p| p.id == plugin) {
+            Some(plugin) => plugin.name.clone(),
+            None => return,
+        };
+        let ed = self.editor.borrow();
+        ed.transform_to_head(rev, slice::from_mut(&mut cursor));
+        let text = ed.get_buffer();
+        self.view.borrow_mut().set_decoration_cursor(
+            text,
+            self.client,
+            plugin,
+            &plugin_name,
+            cursor,
+        );
+    }
+
+    fn do_remove_decoration_cursor(&mut self, plugin: PluginId, id: &str) {
+        let plugin_name = match self.plugins.iter().find(|p| p.id == plugin) {
+            Some(plugin) => plugin.name.clone(),
+            None => return,
+        };
+        let ed = self.editor.borrow();
+        let text = ed.get_buffer();
+        self.view.borrow_mut().remove_decoration_cursor(
+            text,
+            self.client,
+            plugin,
+            &plugin_name,
+            id,
+        );
+    }
+
+    /// Sets a plugin's mark `name`, placed at revision `rev`. The mark is
+    /// kept with the buffer, by the plugin's name.
+    fn do_set_named_mark(&mut self, plugin: PluginId, name: &str, offset: usize, rev: u64) {
+        let plugin_name = match self.plugins.iter().find(|p| p.id == plugin) {
+            Some(plugin) => &plugin.name,
+            None => {
+                warn!("named mark from unknown plugin {:?}", plugin);
+                return;
+            }
+        };
+        let mut ed = self.editor.borrow_mut();
+        if let Some(offset) = ed.offset_at_head(offset, rev) {
+            ed.get_marks_mut().set(plugin_name, name, offset);
+        }
+    }
+
+    fn do_remove_named_mark(&mut self, plugin: PluginId, name: &str) {
+        match self.plugins.iter().find(|p| p.id == plugin) {
+            Some(p) => self.editor.borrow_mut().get_marks_mut().remove(&p.name, name),
+            None => warn!("named mark from unknown plugin {:?}", plugin),
+        }
+    }
+
+    /// Places a plugin's marker, at revision `rev`. Markers last as long as
+    /// the plugin's process.
+    fn do_set_marker(&mut self, plugin: PluginId, id: MarkerId, offset: usize, rev: u64) {
+        let mut ed = self.editor.borrow_mut();
+        if let Some(offset) = ed.offset_at_head(offset, rev) {
+            ed.get_marks_mut().set_marker(plugin, id, offset);
+        }
+    }
+
+    /// Inserts `text` at the current offset of a plugin's marker, in a new
+    /// undo group. The edit is made against the head revision, so it lands
+    /// where the marker is now, even if the plugin hasn't yet seen the
+    /// edits that moved it.
+    fn do_insert_at_marker(
+        &mut self,
+        plugin: PluginId,
+        marker: MarkerId,
+        text: String,
+        priority: u64,
+        author: String,
+    ) {
+        let edit = {
+            let ed = self.editor.borrow();
+            let offset = match ed.get_marks().marker(plugin, marker) {
+                Some(offset) => offset,
+                None => {
+                    warn!("insert at unknown marker {:?} from {:?}", marker, plugin);
+                    return;
+                }
+            };
+            let iv = Interval::new(offset, offset);
+            PluginEdit {
+                rev: ed.get_head_rev_token(),
+                delta: RopeDelta::simple_edit(iv, text.into(), ed.get_buffer().len()),
+                priority: self.engine_priority(plugin, priority),
+                after_cursor: false,
+                undo_group: None,
+                author,
+                auto_pair: false,
+                chunk_size: None,
             }
-            GetSelections => json!("not implemented"),
+        };
+        self.with_editor(|ed, _, _, _| ed.apply_plugin_edit(edit));
+    }
+
+    /// Applies an edit made against an earlier revision, failing if it
+    /// overlaps any change made since.
+    fn do_rebased_edit(
+        &mut self,
+        plugin: PluginId,
+        mut edit: PluginEdit,
+    ) -> Result<Value, RemoteError> {
+        edit.priority = self.engine_priority(plugin, edit.priority);
+        if !self.with_editor(|ed, _, _, _| ed.apply_rebased_edit(edit)) {
+            return Err(RemoteError::custom(409, "edit conflicts with a later change", None));
         }
+        self.after_edit(&plugin.to_string());
+        self.render_if_needed();
+        Ok(Value::Null)
+    }
+
+    /// Applies an edit from a plugin. Chunked edits are queued, and applied
+    /// a chunk at a time when idle. Returns the pairs closed by an
+    /// auto-pairing edit, as the offset between each pair's halves and the
+    /// length of its closing half.
+    fn do_plugin_edit(&mut self, plugin: PluginId, mut edit: PluginEdit) -> Vec<(usize, usize)> {
+        edit.priority = self.engine_priority(plugin, edit.priority);
+        match edit.chunk_size {
+            Some(_) if edit.auto_pair => {
+                warn!("rejecting chunked auto-pair edit from {:?}", plugin)
+            }
+            Some(chunk_size) => {
+                let idle = !self.editor.borrow().has_pending_chunks();
+                self.with_editor(|ed, _, _, _| ed.queue_chunked_edit(edit, chunk_size));
+                if idle {
+                    self.schedule_plugin_edit_chunk();
+                }
+            }
+            None if edit.auto_pair => {
+                return self.with_editor(|ed, _, _, config| {
+                    ed.apply_plugin_edit_paired(edit, &config.surrounding_pairs)
+                });
+            }
+            None => self.with_editor(|ed, _, _, _| ed.apply_plugin_edit(edit)),
+        }
+        Vec::new()
+    }
+
+    /// Returns the priority the engine gives an edit of `priority` from
+    /// `plugin`, which also depends on the plugin's manifest priority.
+    fn engine_priority(&self, plugin: PluginId, priority: u64) -> u64 {
+        let plugin_priority =
+            self.plugins.iter().find(|p| p.id == plugin).map_or(0, |p| p.priority);
+        editor::engine_priority(priority, plugin_priority)
+    }
+
+    /// Moves carets left after auto-closed pairs, as returned by
+    /// `do_plugin_edit`, between the pairs' halves.
+    fn move_carets_into_pairs(&mut self, pairs: &[(usize, usize)]) {
+        self.with_view(|view, text| {
+            let mut selection = Selection::new();
+            for &region in view.sel_regions() {
+                let pair = pairs.iter().find(|&&(between, closing_len)| {
+                    region.is_caret() && region.end == between + closing_len
+                });
+                match pair {
+                    Some(&(between, _)) => selection.add_region(SelRegion::caret(between)),
+                    None => selection.add_region(region),
+                }
+            }
+            view.set_selection(text, selection);
+        });
     }
 
     /// Commits any changes to the buffer, updating views and plugins as needed.
@@ -280,6 +565,7 @@ impl<'a> EventContext<'a> {
 
         self.update_views(&self.editor.borrow(), &delta, &last_text, drift);
         self.update_plugins(&mut self.editor.borrow_mut(), delta, author);
+        self.notify_modified_changed();
 
         //if we have no plugins we always render immediately.
         if !self.plugins.is_empty() {
@@ -393,10 +679,8 @@ impl<'a> EventContext<'a> {
 
     pub(crate) fn finish_init(&mut self, config: &Table) {
         if !self.plugins.is_empty() {
-            let info = self.plugin_info();
-
             self.plugins.iter().for_each(|plugin| {
-                plugin.new_buffer(&info);
+                plugin.new_buffer(&self.plugin_info(&plugin.name));
                 self.plugin_started(plugin);
             });
         }
@@ -429,10 +713,19 @@ impl<'a> EventContext<'a> {
         self.plugins.iter().for_each(|plugin| plugin.did_save(self.view_id, path));
 
         self.editor.borrow_mut().set_pristine();
+        self.notify_modified_changed();
         self.with_view(|view, text| view.set_dirty(text));
         self.render()
     }
 
+    /// Tells plugins if the buffer has become modified, or unmodified,
+    /// since they were last told.
+    fn notify_modified_changed(&self) {
+        if let Some(pristine) = self.editor.borrow_mut().take_pristine_change() {
+            self.plugins.iter().for_each(|plugin| plugin.modified_changed(self.view_id, !pristine));
+        }
+    }
+
     /// Returns `true` if this was the last view
     pub(crate) fn close_view(&self) -> bool {
         // we probably want to notify plugins _before_ we close the view
@@ -441,6 +734,14 @@ impl<'a> EventContext<'a> {
         self.siblings.is_empty()
     }
 
+    pub(crate) fn view_focused(&self) {
+        self.plugins.iter().for_each(|plug| plug.view_focused(self.view_id));
+    }
+
+    pub(crate) fn view_blurred(&self) {
+        self.plugins.iter().for_each(|plug| plug.view_blurred(self.view_id));
+    }
+
     pub(crate) fn config_changed(&mut self, changes: &Table) {
         if changes.contains_key("wrap_width") || changes.contains_key("word_wrap") {
             // FIXME: if switching from measurement-based widths to columnar widths,
@@ -473,7 +774,8 @@ impl<'a> EventContext<'a> {
         self.render();
     }
 
-    pub(crate) fn plugin_info(&mut self) -> PluginBufferInfo {
+    /// Returns the buffer information sent to the plugin named `plugin`.
+    pub(crate) fn plugin_info(&self, plugin: &str) -> PluginBufferInfo {
         let ed = self.editor.borrow();
         let nb_lines = ed.get_buffer().measure::<LinesMetric>() + 1;
         let views: Vec<ViewId> = iter::once(&self.view)
@@ -483,7 +785,7 @@ impl<'a> EventContext<'a> {
 
         let changes = serde_json::to_value(self.config).unwrap();
         let path = self.info.map(|info| info.path.to_owned());
-        PluginBufferInfo::new(
+        let mut info = PluginBufferInfo::new(
             self.buffer_id,
             &views,
             ed.get_head_rev_token(),
@@ -492,7 +794,9 @@ impl<'a> EventContext<'a> {
             path,
             self.language.clone(),
             changes.as_object().unwrap().to_owned(),
-        )
+        );
+        info.named_marks = ed.get_marks().named(plugin);
+        info
     }
 
     pub(crate) fn plugin_started(&self, plugin: &Plugin) {
@@ -501,13 +805,36 @@ impl<'a> EventContext<'a> {
 
     pub(crate) fn plugin_stopped(&mut self, plugin: &Plugin) {
         self.client.plugin_stopped(self.view_id, &plugin.name, 0);
+        self.clear_contributions(plugin.id, &plugin.name);
+        self.editor.borrow_mut().get_marks_mut().remove_markers(plugin.id);
+    }
+
+    /// Removes everything `plugin` has added to this view: its style
+    /// layer, annotations, inlay hints, decoration cursors and status items,
+    /// along with its pending prompts and running tasks.
+    fn clear_contributions(&mut self, plugin: PluginId, plugin_name: &str) {
+        let removed = self.view.borrow_mut().clear_plugin_contributions(plugin);
+        if removed.inlay_hints {
+            self.client.set_inlay_hints(self.view_id, plugin_name, &[]);
+        }
+        if removed.decoration_cursors {
+            self.client.set_decoration_cursors(self.view_id, plugin_name, &[]);
+        }
+        for key in &removed.status_keys {
+            self.client.remove_status_item(self.view_id, key);
+        }
+        for id in self.view.borrow_mut().remove_plugin_prompts(plugin) {
+            self.client.hide_prompt(self.view_id, id);
+        }
+        for id in self.view.borrow_mut().remove_plugin_tasks(plugin) {
+            self.client.task_finished(self.view_id, id);
+        }
         let needs_render = self.with_editor(|ed, view, _, _| {
-            if ed.get_layers_mut().remove_layer(plugin.id).is_some() {
+            let had_layer = ed.get_layers_mut().remove_layer(plugin).is_some();
+            if had_layer || removed.annotations {
                 view.set_dirty(ed.get_buffer());
-                true
-            } else {
-                false
             }
+            had_layer || removed.annotations
         });
         if needs_render {
             self.render();
@@ -592,6 +919,26 @@ impl<'a> EventContext<'a> {
         self.render_if_needed();
     }
 
+    /// Applies the next chunk of a chunked plugin edit, updating the
+    /// frontend, and schedules the one after it.
+    pub(crate) fn do_plugin_edit_chunk(&mut self) {
+        let author = match self.with_editor(|ed, _, _, _| ed.apply_next_chunk()) {
+            Some(author) => author,
+            None => return,
+        };
+        self.after_edit(&author);
+        self.render();
+        if self.editor.borrow().has_pending_chunks() {
+            self.schedule_plugin_edit_chunk();
+        }
+    }
+
+    fn schedule_plugin_edit_chunk(&self) {
+        let view_id: usize = self.view_id.into();
+        let token = EDIT_CHUNK_VIEW_IDLE_MASK | view_id;
+        self.client.schedule_idle(token);
+    }
+
     fn schedule_find(&self) {
         let view_id: usize = self.view_id.into();
         let token = FIND_VIEW_IDLE_MASK | view_id;
@@ -689,6 +1036,76 @@ impl<'a> EventContext<'a> {
         }
     }
 
+    /// Forwards the user's answer to a prompt to the plugin that asked.
+    fn do_prompt_response(&mut self, id: usize, result: Option<String>) {
+        let (plugin, request_id) = match self.view.borrow_mut().take_pending_prompt(id) {
+            Some(pending) => pending,
+            None => {
+                warn!("response for unknown prompt {}", id);
+                return;
+            }
+        };
+        if let Some(plugin) = self.plugins.iter().find(|p| p.id == plugin) {
+            plugin.prompt_response(self.view_id, request_id, result.as_ref().map(String::as_str));
+        }
+    }
+
+    /// Asks the plugin running a task to cancel it. The task stays shown
+    /// until the plugin finishes it.
+    fn do_cancel_task(&mut self, id: usize) {
+        let (plugin, task_id) = match self.view.borrow().task_owner(id) {
+            Some(task) => task,
+            None => {
+                warn!("cancel for unknown task {}", id);
+                return;
+            }
+        };
+        if let Some(plugin) = self.plugins.iter().find(|p| p.id == plugin) {
+            plugin.cancel_task(self.view_id, task_id);
+        }
+    }
+
+    /// Offers dropped files to the plugins in `remaining` in turn, skipping
+    /// any that have stopped; the first to accept the drop consumes it. If
+    /// none do, the client is asked to open the files.
+    fn do_files_dropped(&mut self, paths: Vec<PathBuf>, offset: usize, remaining: Vec<PluginId>) {
+        let mut remaining = remaining.into_iter();
+        let plugin = loop {
+            match remaining.next() {
+                Some(id) => {
+                    if let Some(plugin) = self.plugins.iter().find(|p| p.id == id) {
+                        break plugin;
+                    }
+                }
+                None => {
+                    self.client.open_files(&paths);
+                    return;
+                }
+            }
+        };
+        let weak_core = self.weak_core.clone();
+        let view_id = self.view_id;
+        let dropped = paths.clone();
+        let remaining = remaining.collect();
+        plugin.files_dropped(view_id, &paths, offset, move |resp| {
+            weak_core.handle_files_dropped(view_id, dropped, offset, remaining, resp);
+        });
+    }
+
+    /// Handles a plugin's response to `files_dropped`, offering the drop to
+    /// the `remaining` plugins unless it was consumed.
+    pub(crate) fn do_files_dropped_response(
+        &mut self,
+        paths: Vec<PathBuf>,
+        offset: usize,
+        remaining: Vec<PluginId>,
+        response: Result<Value, RpcError>,
+    ) {
+        if !drop_handled(&response) {
+            self.do_files_dropped(paths, offset, remaining);
+        }
+    }
+
     fn do_show_hover(&mut self, request_id: usize, hover: Result<Hover, RemoteError>) {
         match hover {
             Ok(hover) => {
@@ -709,6 +1126,19 @@ impl<'a> EventContext<'a> {
     }
 }
 
+/// Returns `true` if a plugin's response to `files_dropped` says it
+/// consumed the drop. Errors count as declining it.
+fn drop_handled(response: &Result<Value, RpcError>) -> bool {
+    match response {
+        Ok(Value::Bool(handled)) => *handled,
+        Ok(_) => false,
+        Err(err) => {
+            warn!("files_dropped failed: {:?}", err);
+            false
+        }
+    }
+}
+
 #[cfg(test)]
 #[rustfmt::skip]
 mod tests {
@@ -716,18 +1146,47 @@ mod tests {
     use crate::config::ConfigManager;
     use crate::core::dummy_weak_core;
     use crate::tabs::BufferId;
+    use std::sync::{Arc, Mutex};
     use xi_rpc::test_utils::DummyPeer;
+    use xi_rpc::{Callback, Peer};
+
+    /// A peer that records the notifications sent to the frontend, and
+    /// otherwise behaves like `DummyPeer`.
+    #[derive(Clone, Default)]
+    struct RecordingPeer(Arc<Mutex<Vec<(String, Value)>>>);
+
+    impl Peer for RecordingPeer {
+        fn box_clone(&self) -> Box<dyn Peer> {
+            Box::new(self.clone())
+        }
+        fn send_rpc_notification(&self, method: &str, params: &Value) {
+            self.0.lock().unwrap().push((method.to_owned(), params.clone()));
+        }
+        fn send_rpc_request_async(&self, method: &str, params: &Value, f: Box<dyn Callback>) {
+            DummyPeer.send_rpc_request_async(method, params, f)
+        }
+        fn send_rpc_request(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+            DummyPeer.send_rpc_request(method, params)
+        }
+        fn request_is_pending(&self) -> bool {
+            false
+        }
+        fn schedule_idle(&self, _token: usize) {}
+        fn schedule_timer(&self, _after: Instant, _token: usize) {}
+    }
 
     struct ContextHarness {
         view: RefCell<View>,
         editor: RefCell<Editor>,
         client: Client,
+        client_peer: RecordingPeer,
         core_ref: WeakXiCore,
         kill_ring: RefCell<Rope>,
         style_map: RefCell<ThemeStyleMap>,
         width_cache: RefCell<WidthCache>,
         config_manager: ConfigManager,
         recorder: RefCell<Recorder>,
+        plugins: Vec<Plugin>,
     }
 
     impl ContextHarness {
@@ -740,14 +1199,16 @@ mod tests {
             let config = config_manager.add_buffer(buffer_id, None);
             let view = RefCell::new(View::new(view_id, buffer_id));
             let editor = RefCell::new(Editor::with_text(s));
-            let client = Client::new(Box::new(DummyPeer));
+            let client_peer = RecordingPeer::default();
+            let client = Client::new(Box::new(client_peer.clone()));
             let core_ref = dummy_weak_core();
             let kill_ring = RefCell::new(Rope::from(""));
             let style_map = RefCell::new(ThemeStyleMap::new(None));
             let width_cache = RefCell::new(WidthCache::new());
             let recorder = RefCell::new(Recorder::new());
-            let harness = ContextHarness { view, editor, client, core_ref, kill_ring,
-                             style_map, width_cache, config_manager, recorder };
+            let plugins = Vec::new();
+            let harness = ContextHarness { view, editor, client, client_peer, core_ref, kill_ring,
+                             style_map, width_cache, config_manager, recorder, plugins };
             harness.make_context().view_init();
             harness.make_context().finish_init(&config);
             harness
@@ -774,6 +1235,12 @@ mod tests {
             text
         }
 
+        /// Returns the methods of the notifications sent to the frontend
+        /// since the last call, with their params.
+        fn take_client_rpcs(&self) -> Vec<(String, Value)> {
+            self.client_peer.0.lock().unwrap().drain(..).collect()
+        }
+
         fn make_context<'a>(&'a self) -> EventContext<'a> {
             let view_id = ViewId(1);
             let buffer_id = self.view.borrow().get_buffer_id();
@@ -788,7 +1255,7 @@ mod tests {
                 language,
                 info: None,
                 siblings: Vec::new(),
-                plugins: Vec::new(),
+                plugins: self.plugins.iter().collect(),
                 recorder: &self.recorder,
                 client: &self.client,
                 kill_ring: &self.kill_ring,
@@ -1971,6 +2438,8 @@ mod tests {
             after_cursor: false,
             undo_group: None,
             author: "plugin_one".into(),
+            auto_pair: false,
+            chunk_size: None,
         };
 
         ctx.do_plugin_cmd(PluginPid(1), PluginNotification::Edit { edit: edit_one });
@@ -1979,6 +2448,333 @@ mod tests {
         assert_eq!(rev_token, new_rev_token);
     }
 
+    #[test]
+    fn plugin_edit_auto_pair() {
+        use xi_rope::DeltaBuilder;
+        use crate::plugins::rpc::{PluginNotification, PluginEdit};
+        use crate::plugins::PluginPid;
+
+        // a pair the plugin inserts whole is not treated as auto-closed
+        let cases = [(false, "(", "(|"), (true, "(", "(|)"), (true, "()", "()|")];
+        for &(auto_pair, inserted, expected) in &cases {
+            let harness = ContextHarness::new("");
+            let mut ctx = harness.make_context();
+            let mut builder = DeltaBuilder::new(0);
+            builder.replace(Interval::new(0, 0), inserted.into());
+
+            let edit = PluginEdit {
+                rev: ctx.editor.borrow().get_head_rev_token(),
+                delta: builder.build(),
+                priority: 55,
+                after_cursor: false,
+                undo_group: None,
+                author: "plugin_one".into(),
+                auto_pair,
+                chunk_size: None,
+            };
+
+            ctx.do_plugin_cmd(PluginPid(1), PluginNotification::Edit { edit });
+            assert_eq!(harness.debug_render(), expected);
+        }
+    }
+
+
+    #[test]
+    fn chunked_plugin_edit_applied_when_idle() {
+        use crate::plugins::rpc::{PluginEdit, PluginNotification};
+        use crate::plugins::PluginPid;
+
+        let harness = ContextHarness::new("hello");
+        let mut ctx = harness.make_context();
+        let rev = ctx.editor.borrow().get_head_rev_token();
+        let edit = |auto_pair| PluginEdit {
+            rev,
+            delta: RopeDelta::simple_edit(Interval::new(5, 5), " world".into(), 5),
+            priority: 55,
+            after_cursor: false,
+            undo_group: None,
+            author: "plugin_one".into(),
+            auto_pair,
+            chunk_size: Some(2),
+        };
+
+        // an edit can't be both chunked and auto-paired
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::Edit { edit: edit(true) });
+        assert!(!ctx.editor.borrow().has_pending_chunks());
+
+        // nothing is applied until core is idle, then a chunk at a time
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::Edit { edit: edit(false) });
+        assert_eq!(harness.debug_render(), "|hello");
+        ctx.do_plugin_edit_chunk();
+        assert_eq!(harness.debug_render(), "|hello w");
+        while ctx.editor.borrow().has_pending_chunks() {
+            ctx.do_plugin_edit_chunk();
+        }
+        assert_eq!(harness.debug_render(), "|hello world");
+    }
+
+    #[test]
+    fn plugin_duplicate_selection() {
+        use crate::rpc::GestureType::*;
+        use crate::plugins::rpc::PluginNotification;
+        use crate::plugins::PluginPid;
+
+        let harness = ContextHarness::new("one\ntwo\nthree");
+        let mut ctx = harness.make_context();
+        ctx.do_edit(EditNotification::Gesture { line: 1, col: 1, ty: PointSelect });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::DuplicateSelection);
+        assert_eq!(harness.debug_render(), "one\ntwo\nt|wo\nthree");
+
+        ctx.do_edit(EditNotification::Gesture { line: 3, col: 1, ty: PointSelect });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::DuplicateSelection);
+        assert_eq!(harness.debug_render(), "one\ntwo\ntwo\nthree\nt|hree");
+
+        ctx.do_edit(EditNotification::Gesture { line: 0, col: 0, ty: PointSelect });
+        ctx.do_edit(EditNotification::MoveRightAndModifySelection);
+        ctx.do_edit(EditNotification::MoveRightAndModifySelection);
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::DuplicateSelection);
+        assert_eq!(harness.debug_render(), "on[on|]e\ntwo\ntwo\nthree\nthree");
+
+        ctx.do_edit(EditNotification::Undo);
+        assert_eq!(ctx.editor.borrow().get_buffer().to_string(), "one\ntwo\ntwo\nthree\nthree");
+    }
+
+    #[test]
+    fn duplicate_line() {
+        use crate::rpc::GestureType::*;
+
+        let harness = ContextHarness::new("one\ntwo\nthree");
+        let mut ctx = harness.make_context();
+        ctx.do_edit(EditNotification::Gesture { line: 0, col: 1, ty: PointSelect });
+        ctx.do_edit(EditNotification::DuplicateLine);
+        assert_eq!(harness.debug_render(), "one\no|ne\ntwo\nthree");
+
+        // the last line has no line ending to copy
+        ctx.do_edit(EditNotification::Gesture { line: 3, col: 1, ty: PointSelect });
+        ctx.do_edit(EditNotification::DuplicateLine);
+        assert_eq!(harness.debug_render(), "one\none\ntwo\nthree\nt|hree");
+    }
+
+    #[test]
+    fn plugin_get_selections() {
+        use crate::rpc::GestureType::*;
+        use crate::plugins::rpc::{PluginRequest, Selections};
+        use crate::plugins::PluginPid;
+
+        let harness = ContextHarness::new("one\ntwo\nthree");
+        let mut ctx = harness.make_context();
+        ctx.do_edit(EditNotification::Gesture { line: 0, col: 1, ty: PointSelect });
+        ctx.do_edit(EditNotification::AddSelectionBelow);
+        ctx.do_edit(EditNotification::AddSelectionBelow);
+        let result = ctx.do_plugin_cmd_sync(PluginPid(1), PluginRequest::GetSelections).unwrap();
+        let sel: Selections = serde_json::from_value(result).unwrap();
+        assert_eq!(sel.regions, vec![(1, 1), (5, 5), (9, 9)]);
+        assert_eq!(sel.primary, 2);
+    }
+
+    #[test]
+    fn inlay_hints_follow_edits() {
+        use crate::plugins::rpc::{InlayHint, InlayHintPosition};
+        use crate::plugins::PluginPid;
+
+        let mut harness = ContextHarness::new("let x = 1;");
+        harness.plugins.push(Plugin::with_peer(Box::new(DummyPeer), PluginPid(1), "types", 0));
+        let mut ctx = harness.make_context();
+        let rev = ctx.editor.borrow().get_head_rev_token();
+        let hints = vec![InlayHint {
+            offset: 5, label: ": u32".into(), position: InlayHintPosition::After, padding: false }];
+        let sent_hints = || harness.take_client_rpcs().into_iter()
+            .filter(|(method, _)| method == "set_inlay_hints")
+            .map(|(_, params)| params["hints"].clone())
+            .collect::<Vec<_>>();
+
+        // hints computed before an edit are moved past it
+        ctx.do_edit(EditNotification::Insert { chars: "// a\n".into() });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetInlayHints { hints, rev });
+        assert_eq!(sent_hints(), vec![json!([
+            {"line": 1, "col": 5, "label": ": u32", "position": "after", "padding": false}])]);
+
+        // and core sends them again when an edit moves them
+        ctx.do_edit(EditNotification::InsertNewline);
+        assert_eq!(sent_hints()[0][0]["line"], json!(2));
+        ctx.do_edit(EditNotification::MoveToEndOfDocument);
+        ctx.do_edit(EditNotification::Insert { chars: " // b".into() });
+        assert!(sent_hints().is_empty());
+
+        // clearing the plugin's contributions tells the frontend, once
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::ClearContributions);
+        assert_eq!(sent_hints(), vec![json!([])]);
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::ClearContributions);
+        assert!(sent_hints().is_empty());
+    }
+
+    #[test]
+    fn decoration_cursors_follow_edits() {
+        use crate::plugins::rpc::{Color, DecorationCursor};
+        use crate::plugins::PluginPid;
+
+        let mut harness = ContextHarness::new("hello world");
+        harness.plugins.push(Plugin::with_peer(Box::new(DummyPeer), PluginPid(1), "collab", 0));
+        let mut ctx = harness.make_context();
+        let rev = ctx.editor.borrow().get_head_rev_token();
+        let red = Color::rgb(0xff, 0, 0);
+        let cursor = |id: &str, offset| DecorationCursor { id: id.into(), offset, color: red };
+        let sent_cursors = || harness.take_client_rpcs().into_iter()
+            .filter(|(method, _)| method == "set_decoration_cursors")
+            .map(|(_, params)| params["cursors"].clone())
+            .collect::<Vec<_>>();
+
+        // a cursor placed before an edit is moved past it
+        ctx.do_edit(EditNotification::Insert { chars: "ah, ".into() });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetDecorationCursor {
+            cursor: cursor("alice", 6), rev });
+        assert_eq!(sent_cursors(), vec![json!([
+            {"id": "alice", "line": 0, "col": 10, "color": 0xffff_0000u32}])]);
+
+        // setting a cursor again moves it, and other cursors are kept
+        let rev = ctx.editor.borrow().get_head_rev_token();
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetDecorationCursor {
+            cursor: cursor("bob", 2), rev });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetDecorationCursor {
+            cursor: cursor("alice", 4), rev });
+        let sent = sent_cursors();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].as_array().unwrap().iter().map(|c| &c["col"]).collect::<Vec<_>>(),
+                   vec![&json!(4), &json!(2)]);
+
+        // edits move the cursors, and core sends them again
+        ctx.do_edit(EditNotification::MoveToBeginningOfDocument);
+        ctx.do_edit(EditNotification::InsertNewline);
+        assert_eq!(sent_cursors()[0][0]["line"], json!(1));
+
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::RemoveDecorationCursor {
+            id: "alice".into() });
+        assert_eq!(sent_cursors(), vec![json!([
+            {"id": "bob", "line": 1, "col": 2, "color": 0xffff_0000u32}])]);
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::RemoveDecorationCursor {
+            id: "alice".into() });
+        assert!(sent_cursors().is_empty());
+    }
+
+    #[test]
+    fn insert_at_markers() {
+        use crate::plugins::rpc::MarkerId;
+        use crate::plugins::PluginPid;
+
+        let mut harness = ContextHarness::new("fn f(a) {}");
+        harness.plugins.push(Plugin::with_peer(Box::new(DummyPeer), PluginPid(1), "snippets", 0));
+        let mut ctx = harness.make_context();
+        let rev = ctx.editor.borrow().get_head_rev_token();
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetMarker {
+            id: MarkerId(0), offset: 6, rev });
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::SetMarker {
+            id: MarkerId(1), offset: 9, rev });
+        let insert = |id, text: &str| PluginNotification::InsertAtMarker {
+            marker: MarkerId(id), text: text.into(), priority: 0, author: "snippets".into() };
+
+        // the first insert moves the second marker, which the plugin hasn't seen
+        ctx.do_plugin_cmd(PluginPid(1), insert(0, ", b"));
+        ctx.do_plugin_cmd(PluginPid(1), insert(1, " a + b "));
+        assert_eq!(ctx.editor.borrow().get_buffer().to_string(), "fn f(a, b) { a + b }");
+
+        // each insert is its own undo group
+        ctx.do_edit(EditNotification::Undo);
+        assert_eq!(ctx.editor.borrow().get_buffer().to_string(), "fn f(a, b) {}");
+
+        // markers are per plugin
+        ctx.do_plugin_cmd(PluginPid(2), insert(0, "x"));
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::RemoveMarker { id: MarkerId(0) });
+        ctx.do_plugin_cmd(PluginPid(1), insert(0, "x"));
+        assert_eq!(ctx.editor.borrow().get_buffer().to_string(), "fn f(a, b) {}");
+    }
+
+    #[test]
+    fn files_dropped() {
+        use crate::plugins::PluginPid;
+
+        let paths = vec![PathBuf::from("notes.md")];
+        let dropped = || EditNotification::FilesDropped { paths: paths.clone(), position: None };
+        let opened = |harness: &ContextHarness| harness.take_client_rpcs().into_iter()
+            .filter(|(method, _)| method == "open_files")
+            .map(|(_, params)| params["paths"].clone())
+            .collect::<Vec<_>>();
+
+        // with no plugin to take the drop, the frontend opens the files
+        let harness = ContextHarness::new("");
+        harness.make_context().do_edit(dropped());
+        assert_eq!(opened(&harness), vec![json!(["notes.md"])]);
+
+        let mut harness = ContextHarness::new("");
+        harness.plugins.push(Plugin::with_peer(Box::new(DummyPeer), PluginPid(1), "notes", 0));
+        let mut ctx = harness.make_context();
+        // the drop is offered to the plugin instead
+        ctx.do_edit(dropped());
+        assert!(opened(&harness).is_empty());
+        // which consumes it
+        ctx.do_files_dropped_response(paths.clone(), 0, Vec::new(), Ok(json!(true)));
+        assert!(opened(&harness).is_empty());
+        // or declines it, with another plugin still to ask
+        ctx.do_files_dropped_response(paths.clone(), 0, vec![PluginPid(1)], Ok(json!(false)));
+        assert!(opened(&harness).is_empty());
+        // or fails, with only a plugin left that has since stopped
+        let response = Err(RpcError::PeerDisconnect);
+        ctx.do_files_dropped_response(paths.clone(), 0, vec![PluginPid(2)], response);
+        assert_eq!(opened(&harness), vec![json!(["notes.md"])]);
+        ctx.do_files_dropped_response(paths.clone(), 0, Vec::new(), Ok(json!(false)));
+        assert_eq!(opened(&harness), vec![json!(["notes.md"])]);
+        // only `true` consumes the drop
+        ctx.do_files_dropped_response(paths.clone(), 0, Vec::new(), Ok(Value::Null));
+        assert_eq!(opened(&harness), vec![json!(["notes.md"])]);
+    }
+
+    #[test]
+    fn stopped_plugin_prompts_and_tasks_cleared() {
+        use crate::plugins::PluginPid;
+
+        let harness = ContextHarness::new("");
+        let mut ctx = harness.make_context();
+        let prompt = |request_id| PluginNotification::Prompt {
+            request_id, title: "Name".into(), default: None };
+        ctx.do_plugin_cmd(PluginPid(1), prompt(0));
+        ctx.do_plugin_cmd(PluginPid(1), PluginNotification::BeginTask {
+            task_id: 0, title: "Indexing".into() });
+        ctx.do_plugin_cmd(PluginPid(2), prompt(0));
+        let shown: Vec<_> = harness.take_client_rpcs().into_iter()
+            .filter(|(method, _)| method == "show_prompt" || method == "task_started")
+            .map(|(_, params)| params)
+            .collect();
+        let (first_prompt, task, second_prompt) =
+            (&shown[0]["request_id"], &shown[1]["task_id"], &shown[2]["request_id"]);
+
+        ctx.clear_contributions(PluginPid(1), "plugin_one");
+        let sent = harness.take_client_rpcs();
+        let hidden: Vec<_> = sent.iter().filter(|(method, _)| method == "hide_prompt").collect();
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(&hidden[0].1["request_id"], first_prompt);
+        let finished: Vec<_> = sent.iter().filter(|(method, _)| method == "task_finished").collect();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(&finished[0].1["task_id"], task);
+
+        let mut view = harness.view.borrow_mut();
+        assert_eq!(view.task_id(PluginPid(1), 0), None);
+        assert_eq!(view.take_pending_prompt(first_prompt.as_u64().unwrap() as usize), None);
+        let second_prompt = second_prompt.as_u64().unwrap() as usize;
+        assert_eq!(view.take_pending_prompt(second_prompt), Some((PluginPid(2), 0)));
+    }
+
+    #[test]
+    fn plugin_info_has_current_config() {
+        use crate::config::ConfigDomain;
+
+        let mut harness = ContextHarness::new("");
+        let table = json!({"tab_size": 2}).as_object().unwrap().to_owned();
+        harness.config_manager.set_user_config(ConfigDomain::General, table).unwrap();
+
+        let info = harness.make_context().plugin_info("test");
+        assert_eq!(info.config.get("tab_size"), Some(&json!(2)));
+        // the whole table is sent, not only the settings that were changed
+        assert_eq!(info.config.get("word_wrap"), Some(&json!(false)));
+    }
 
     #[test]
     fn empty_transpose() {
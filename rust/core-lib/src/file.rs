@@ -14,10 +14,12 @@
 
 //! Interactions with the file system.
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fmt;
 use std::fs::{self, File};
+use std::hash::Hasher;
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::str;
@@ -37,6 +39,9 @@ use std::{fs::Permissions, os::unix::fs::PermissionsExt};
 
 const UTF8_BOM: &str = "\u{feff}";
 
+/// The size of the largest file `content_hash` will hash.
+pub(crate) const MAX_HASH_SIZE: u64 = 16 * 1024 * 1024;
+
 /// Tracks all state related to open files.
 pub struct FileManager {
     open_files: HashMap<PathBuf, BufferId>,
@@ -269,6 +274,38 @@ fn get_mod_time<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
     File::open(path).and_then(|f| f.metadata()).and_then(|meta| meta.modified()).ok()
 }
 
+/// Returns a hash of the contents of the file at a given path, or `None` if
+/// there is no such file. The file is read in chunks, rather than all at once.
+///
+/// This runs on the core thread, so files larger than `MAX_HASH_SIZE` are
+/// not hashed: an error is returned instead.
+///
+/// Hashes are only comparable within one run of core: `DefaultHasher`'s
+/// algorithm is unspecified, and may change between Rust versions, so they
+/// should not be stored.
+pub(crate) fn content_hash<P: AsRef<Path>>(path: P) -> io::Result<Option<u64>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if file.metadata()?.len() > MAX_HASH_SIZE {
+        let msg = format!("file is larger than {} bytes, and can't be hashed", MAX_HASH_SIZE);
+        return Err(io::Error::new(io::ErrorKind::Other, msg));
+    }
+    let mut hasher = DefaultHasher::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => hasher.write(&buf[..len]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(hasher.finish()))
+}
+
 /// Returns the file permissions for the file at a given path on UNIXy systems,
 /// if present.
 #[cfg(target_family = "unix")]
@@ -310,3 +347,36 @@ impl fmt::Display for FileError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn content_hash_changes_with_file() {
+        let tmp = TempDir::new("xi-test-content-hash").unwrap();
+        let path = tmp.path().join("file.txt");
+        assert_eq!(content_hash(&path).unwrap(), None);
+
+        fs::write(&path, "hello").unwrap();
+        let before = content_hash(&path).unwrap();
+        assert!(before.is_some());
+        assert_eq!(content_hash(&path).unwrap(), before);
+
+        fs::write(&path, "hello, world").unwrap();
+        assert_ne!(content_hash(&path).unwrap(), before);
+
+        // files larger than the read buffer are hashed whole
+        let mut contents = vec![b'a'; 200 * 1024];
+        fs::write(&path, &contents).unwrap();
+        let before = content_hash(&path).unwrap();
+        *contents.last_mut().unwrap() = b'b';
+        fs::write(&path, &contents).unwrap();
+        assert_ne!(content_hash(&path).unwrap(), before);
+
+        // but files over the limit aren't hashed at all
+        File::create(&path).unwrap().set_len(MAX_HASH_SIZE + 1).unwrap();
+        assert!(content_hash(&path).is_err());
+    }
+}
@@ -18,6 +18,7 @@ use std::cmp::{max, min};
 use std::iter;
 
 use crate::annotations::{AnnotationRange, AnnotationSlice, AnnotationType, ToAnnotation};
+use crate::plugins::rpc::SearchState;
 use crate::selection::{InsertDrift, SelRegion, Selection};
 use crate::view::View;
 use crate::word_boundaries::WordCursor;
@@ -130,6 +131,19 @@ impl Find {
         }
     }
 
+    /// Returns the query and the matches found for it, if a query is set.
+    pub(crate) fn search_state(&self, in_progress: bool) -> Option<SearchState> {
+        let query = self.search_string.clone()?;
+        Some(SearchState {
+            query,
+            case_sensitive: self.case_matching == CaseMatching::Exact,
+            is_regex: self.regex.is_some(),
+            whole_words: self.whole_words,
+            matches: self.occurrences.iter().map(|o| (o.min(), o.max())).collect(),
+            in_progress,
+        })
+    }
+
     pub fn set_hls_dirty(&mut self, is_dirty: bool) {
         self.hls_dirty = is_dirty
     }
@@ -64,6 +64,7 @@ mod ledger_includes {
 #[cfg(feature = "ledger")]
 use ledger_includes::*;
 
+pub mod anchors;
 pub mod annotations;
 pub mod backspace;
 pub mod client;
@@ -81,6 +82,7 @@ pub mod layers;
 pub mod line_cache_shadow;
 pub mod line_ending;
 pub mod linewrap;
+pub mod marks;
 pub mod movement;
 pub mod plugins;
 pub mod recorder;
@@ -0,0 +1,160 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Marks and markers that plugins set in a buffer, which core moves with
+//! edits. Named marks are kept when the buffer is closed, so that they can
+//! be restored if its file is opened again; markers are not.
+
+use std::collections::BTreeMap;
+
+use xi_rope::{RopeDelta, Transformer};
+
+use crate::plugins::rpc::MarkerId;
+use crate::plugins::PluginId;
+
+/// The named marks and markers plugins have set in a buffer.
+#[derive(Debug, Default)]
+pub struct Marks {
+    /// The offsets of the marks, by the name of the plugin that set them
+    /// and then by the name of the mark. Plugins are identified by name
+    /// rather than id, so that their marks outlive their process.
+    named: BTreeMap<String, BTreeMap<String, usize>>,
+    /// The offsets of the markers, by the plugin that placed them and id.
+    markers: BTreeMap<(PluginId, MarkerId), usize>,
+}
+
+impl Marks {
+    /// Sets `plugin`'s mark `name` to `offset`, replacing any existing mark
+    /// with that name.
+    pub fn set(&mut self, plugin: &str, name: &str, offset: usize) {
+        self.named.entry(plugin.to_owned()).or_default().insert(name.to_owned(), offset);
+    }
+
+    /// Removes `plugin`'s mark `name`, if it exists.
+    pub fn remove(&mut self, plugin: &str, name: &str) {
+        let now_empty = match self.named.get_mut(plugin) {
+            Some(marks) => marks.remove(name).is_some() && marks.is_empty(),
+            None => false,
+        };
+        if now_empty {
+            self.named.remove(plugin);
+        }
+    }
+
+    /// Returns the marks set by `plugin`, by name.
+    pub fn named(&self, plugin: &str) -> BTreeMap<String, usize> {
+        self.named.get(plugin).cloned().unwrap_or_default()
+    }
+
+    /// Places `plugin`'s marker `id` at `offset`, replacing any existing
+    /// marker with that id.
+    pub fn set_marker(&mut self, plugin: PluginId, id: MarkerId, offset: usize) {
+        self.markers.insert((plugin, id), offset);
+    }
+
+    pub fn remove_marker(&mut self, plugin: PluginId, id: MarkerId) {
+        self.markers.remove(&(plugin, id));
+    }
+
+    /// Returns the offset of `plugin`'s marker `id`, if it exists.
+    pub fn marker(&self, plugin: PluginId, id: MarkerId) -> Option<usize> {
+        self.markers.get(&(plugin, id)).cloned()
+    }
+
+    /// Removes the markers placed by `plugin`.
+    pub fn remove_markers(&mut self, plugin: PluginId) {
+        self.markers.retain(|&(p, _), _| p != plugin);
+    }
+
+    /// Removes the markers placed by every plugin, leaving the named marks.
+    pub fn clear_markers(&mut self) {
+        self.markers.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.named.is_empty() && self.markers.is_empty()
+    }
+
+    /// Moves the marks and markers with an edit. Text inserted at a mark
+    /// goes after it.
+    pub fn apply_delta(&mut self, delta: &RopeDelta) {
+        let mut transformer = Transformer::new(delta);
+        for offset in self.offsets_mut() {
+            *offset = transformer.transform(*offset, false);
+        }
+    }
+
+    /// Moves marks past `len` back to `len`, for a buffer whose text may
+    /// have changed since the marks were set.
+    pub fn clamp(&mut self, len: usize) {
+        for offset in self.offsets_mut() {
+            *offset = (*offset).min(len);
+        }
+    }
+
+    fn offsets_mut(&mut self) -> impl Iterator<Item = &mut usize> {
+        self.named.values_mut().flat_map(BTreeMap::values_mut).chain(self.markers.values_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginPid;
+    use xi_rope::Interval;
+
+    #[test]
+    fn marks_move_with_edits() {
+        let mut marks = Marks::default();
+        marks.set("vim", "a", 6);
+        marks.set("vim", "b", 2);
+        marks.set("lsp", "a", 11);
+        let delta = RopeDelta::simple_edit(Interval::new(2, 2), "abcd".into(), 11);
+        marks.apply_delta(&delta);
+        assert_eq!(marks.named("vim")["a"], 10);
+        assert_eq!(marks.named("vim")["b"], 2);
+        assert_eq!(marks.named("lsp")["a"], 15);
+
+        marks.clamp(12);
+        assert_eq!(marks.named("vim")["a"], 10);
+        assert_eq!(marks.named("lsp")["a"], 12);
+
+        marks.remove("lsp", "a");
+        marks.remove("vim", "a");
+        assert!(marks.named("lsp").is_empty());
+        marks.remove("vim", "b");
+        assert!(marks.is_empty());
+    }
+
+    #[test]
+    fn markers_move_with_edits() {
+        let (a, b) = (PluginPid(1), PluginPid(2));
+        let mut marks = Marks::default();
+        marks.set_marker(a, MarkerId(0), 3);
+        marks.set_marker(b, MarkerId(0), 5);
+        marks.set("vim", "x", 5);
+        let delta = RopeDelta::simple_edit(Interval::new(4, 4), "ab".into(), 11);
+        marks.apply_delta(&delta);
+        assert_eq!(marks.marker(a, MarkerId(0)), Some(3));
+        assert_eq!(marks.marker(b, MarkerId(0)), Some(7));
+        assert_eq!(marks.marker(b, MarkerId(1)), None);
+
+        marks.remove_markers(b);
+        assert_eq!(marks.marker(b, MarkerId(0)), None);
+        assert_eq!(marks.marker(a, MarkerId(0)), Some(3));
+        marks.clear_markers();
+        assert_eq!(marks.marker(a, MarkerId(0)), None);
+        assert_eq!(marks.named("vim")["x"], 7);
+    }
+}
@@ -31,6 +31,14 @@ pub struct PluginDescription {
     pub version: String,
     #[serde(default)]
     pub scope: PluginScope,
+    /// Determines the order in which running plugins are invoked: plugins
+    /// with a higher priority are invoked first, and plugins with equal
+    /// priority are invoked in order of their names. Of two concurrent
+    /// edits with the same edit priority, inserting at the same place, the
+    /// one from the plugin with the higher priority is placed after the
+    /// other.
+    #[serde(default)]
+    pub priority: i32,
     // more metadata ...
     /// path to plugin executable
     #[serde(deserialize_with = "platform_exec_path")]
@@ -252,6 +260,7 @@ mod tests {
         "#;
 
         let plugin_desc: PluginDescription = serde_json::from_str(&json).unwrap();
+        assert_eq!(plugin_desc.priority, 0);
         if cfg!(windows) {
             assert!(plugin_desc.exec_path.ends_with("binary.exe"));
         } else {
@@ -18,9 +18,10 @@ mod catalog;
 pub mod manifest;
 pub mod rpc;
 
+use std::cmp::Reverse;
 use std::fmt;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command as ProcCommand, Stdio};
 use std::sync::Arc;
 use std::thread;
@@ -63,11 +64,25 @@ pub struct Plugin {
     peer: RpcPeer,
     pub(crate) id: PluginId,
     pub(crate) name: String,
+    pub(crate) priority: i32,
+    /// The plugin's process; `None` only for plugins made by tests.
     #[allow(dead_code)]
-    process: Child,
+    process: Option<Child>,
 }
 
 impl Plugin {
+    /// The key by which running plugins are ordered; see
+    /// `PluginDescription::priority`.
+    pub(crate) fn invocation_key(&self) -> (Reverse<i32>, &str) {
+        invocation_key(self.priority, &self.name)
+    }
+
+    /// Makes a plugin that sends its RPCs to `peer` rather than to a process.
+    #[cfg(test)]
+    pub(crate) fn with_peer(peer: RpcPeer, id: PluginId, name: &str, priority: i32) -> Self {
+        Plugin { peer, id, name: name.to_owned(), priority, process: None }
+    }
+
     //TODO: initialize should be sent automatically during launch,
     //and should only send the plugin_id. We can just use the existing 'new_buffer'
     // RPC for adding views
@@ -139,6 +154,34 @@ impl Plugin {
         )
     }
 
+    pub fn view_focused(&self, view_id: ViewId) {
+        self.peer.send_rpc_notification("view_focused", &json!({ "view_id": view_id }))
+    }
+
+    pub fn view_blurred(&self, view_id: ViewId) {
+        self.peer.send_rpc_notification("view_blurred", &json!({ "view_id": view_id }))
+    }
+
+    pub fn modified_changed(&self, view_id: ViewId, is_modified: bool) {
+        self.peer.send_rpc_notification(
+            "modified_changed",
+            &json!({
+                "view_id": view_id,
+                "is_modified": is_modified,
+            }),
+        )
+    }
+
+    pub fn cancel_task(&self, view_id: ViewId, task_id: usize) {
+        self.peer.send_rpc_notification(
+            "cancel_task",
+            &json!({
+                "view_id": view_id,
+                "task_id": task_id,
+            }),
+        )
+    }
+
     pub fn get_hover(&self, view_id: ViewId, request_id: usize, position: usize) {
         self.peer.send_rpc_notification(
             "get_hover",
@@ -150,6 +193,31 @@ impl Plugin {
         )
     }
 
+    /// Asks the plugin whether it handles files dropped onto a view; it
+    /// responds with `true` if it has consumed the drop.
+    pub fn files_dropped<F>(&self, view_id: ViewId, paths: &[PathBuf], offset: usize, callback: F)
+    where
+        F: FnOnce(Result<Value, xi_rpc::Error>) + Send + 'static,
+    {
+        let params = json!({
+            "view_id": view_id,
+            "paths": paths,
+            "offset": offset,
+        });
+        self.peer.send_rpc_request_async("files_dropped", &params, Box::new(callback))
+    }
+
+    pub fn prompt_response(&self, view_id: ViewId, request_id: usize, result: Option<&str>) {
+        self.peer.send_rpc_notification(
+            "prompt_response",
+            &json!({
+                "view_id": view_id,
+                "request_id": request_id,
+                "result": result,
+            }),
+        )
+    }
+
     pub fn dispatch_command(&self, view_id: ViewId, method: &str, params: &Value) {
         self.peer.send_rpc_notification(
             "custom_command",
@@ -183,8 +251,9 @@ pub(crate) fn start_plugin_process(
                     let mut looper = RpcLoop::new(child_stdin);
                     let peer: RpcPeer = Box::new(looper.get_raw_peer());
                     let name = plugin_desc.name.clone();
+                    let priority = plugin_desc.priority;
                     peer.send_rpc_notification("ping", &Value::Array(Vec::new()));
-                    let plugin = Plugin { peer, process: child, name, id };
+                    let plugin = Plugin { peer, process: Some(child), name, priority, id };
 
                     // set tracing immediately
                     if xi_trace::is_enabled() {
@@ -204,3 +273,19 @@ pub(crate) fn start_plugin_process(
         error!("thread spawn failed for {}, {:?}", id, err);
     }
 }
+
+fn invocation_key(priority: i32, name: &str) -> (Reverse<i32>, &str) {
+    (Reverse(priority), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invocation_order() {
+        let mut plugins = vec![(0, "b"), (10, "low"), (0, "a"), (20, "high")];
+        plugins.sort_by(|a, b| invocation_key(a.0, a.1).cmp(&invocation_key(b.0, b.1)));
+        assert_eq!(plugins, vec![(20, "high"), (10, "low"), (0, "a"), (0, "b")]);
+    }
+}
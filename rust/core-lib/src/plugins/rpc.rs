@@ -15,6 +15,7 @@
 //! RPC types, corresponding to protocol requests, notifications & responses.
 
 use std::borrow::Borrow;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use serde::de::{self, Deserialize, Deserializer};
@@ -51,6 +52,10 @@ pub struct PluginBufferInfo {
     pub path: Option<String>,
     pub syntax: LanguageId,
     pub config: Table,
+    /// The named marks the receiving plugin has set in this buffer, kept
+    /// by core across edits and across closing and reopening the file.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub named_marks: BTreeMap<String, usize>,
 }
 
 //TODO: very likely this should be merged with PluginDescription
@@ -97,6 +102,7 @@ pub struct EmptyStruct {}
 pub enum HostRequest {
     Update(PluginUpdate),
     CollectTrace(EmptyStruct),
+    FilesDropped { view_id: ViewId, paths: Vec<PathBuf>, offset: usize },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +121,11 @@ pub enum HostNotification {
     TracingConfig { enabled: bool },
     LanguageChanged { view_id: ViewId, new_lang: LanguageId },
     CustomCommand { view_id: ViewId, method: String, params: Value },
+    PromptResponse { view_id: ViewId, request_id: usize, result: Option<String> },
+    ViewFocused { view_id: ViewId },
+    ViewBlurred { view_id: ViewId },
+    CancelTask { view_id: ViewId, task_id: usize },
+    ModifiedChanged { view_id: ViewId, is_modified: bool },
 }
 
 // ====================================================================
@@ -135,6 +146,17 @@ pub struct PluginEdit {
     /// undo_group associated with this edit
     pub undo_group: Option<usize>,
     pub author: String,
+    /// Whether inserting the opening half of a surrounding pair, such as
+    /// `(`, should also insert the closing half, with the cursor between.
+    #[serde(default)]
+    pub auto_pair: bool,
+    /// If set, a large edit is applied as a series of smaller edits, each
+    /// deleting and inserting at most this many bytes, so the frontend can
+    /// be updated as it progresses. The pieces share a single undo group,
+    /// and are applied when core is idle. Edits that also set `auto_pair`
+    /// are rejected.
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
@@ -160,6 +182,32 @@ pub struct GetDataResponse {
     pub first_line_offset: usize,
 }
 
+/// The state of a view's active search, returned by the `get_search_state` RPC.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchState {
+    /// The search query.
+    pub query: String,
+    pub case_sensitive: bool,
+    pub is_regex: bool,
+    pub whole_words: bool,
+    /// The `(start, end)` offsets of the matches found so far.
+    pub matches: Vec<(usize, usize)>,
+    /// Whether an incremental search is still running, in which case
+    /// `matches` may not yet cover the whole document.
+    pub in_progress: bool,
+}
+
+/// A view's selections, returned by the `get_selections` RPC.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Selections {
+    /// The `(start, end)` offsets of each selection, in document order.
+    /// `end` is the caret; the selection is empty when they are equal.
+    pub regions: Vec<(usize, usize)>,
+    /// The index in `regions` of the primary selection. Core treats the
+    /// last selection as primary, for instance when seeding a search.
+    pub primary: usize,
+}
+
 /// The unit of measure when requesting data.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
@@ -180,6 +228,9 @@ pub enum PluginRequest {
     GetData { start: usize, unit: TextUnit, max_size: usize, rev: u64 },
     LineCount,
     GetSelections,
+    GetSearchState,
+    GetDiskContentHash,
+    RebasedEdit { edit: PluginEdit },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -218,6 +269,73 @@ pub enum PluginNotification {
         request_id: usize,
         result: Result<Hover, RemoteError>,
     },
+    Prompt {
+        request_id: usize,
+        title: String,
+        default: Option<String>,
+    },
+    ShowPeek {
+        anchor: usize,
+        content: PeekContent,
+    },
+    HidePeek,
+    BeginTask {
+        task_id: usize,
+        title: String,
+    },
+    UpdateTask {
+        task_id: usize,
+        message: String,
+        fraction: f64,
+    },
+    FinishTask {
+        task_id: usize,
+    },
+    DuplicateSelection,
+    /// Removes everything the plugin has added to the view.
+    ClearContributions,
+    /// Replaces the plugin's inlay hints, placed at revision `rev`.
+    SetInlayHints {
+        hints: Vec<InlayHint>,
+        rev: u64,
+    },
+    /// Places a decoration cursor in revision `rev`, replacing the plugin's
+    /// cursor with the same id, if any.
+    SetDecorationCursor {
+        cursor: DecorationCursor,
+        rev: u64,
+    },
+    RemoveDecorationCursor {
+        id: String,
+    },
+    /// Sets the plugin's mark `name` to `offset` in revision `rev`,
+    /// replacing any existing mark with that name.
+    SetNamedMark {
+        name: String,
+        offset: usize,
+        rev: u64,
+    },
+    RemoveNamedMark {
+        name: String,
+    },
+    /// Places the plugin's marker `id` at `offset` in revision `rev`,
+    /// replacing any existing marker with that id.
+    SetMarker {
+        id: MarkerId,
+        offset: usize,
+        rev: u64,
+    },
+    RemoveMarker {
+        id: MarkerId,
+    },
+    /// Inserts `text` at the current offset of the plugin's marker
+    /// `marker`, in a new undo group.
+    InsertAtMarker {
+        marker: MarkerId,
+        text: String,
+        priority: u64,
+        author: String,
+    },
     UpdateAnnotations {
         start: usize,
         len: usize,
@@ -227,6 +345,11 @@ pub enum PluginNotification {
     },
 }
 
+/// Identifies a marker placed by a plugin. Each plugin chooses the ids of
+/// its own markers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MarkerId(pub usize);
+
 /// Range expressed in terms of PluginPosition. Meant to be sent from
 /// plugin to core.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -244,6 +367,92 @@ pub struct Hover {
     pub range: Option<Range>,
 }
 
+/// Content shown inline in a view by a plugin, such as the definition of
+/// the symbol under the cursor.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeekContent {
+    /// Lines `start_line..end_line` (zero-based) of the file at `path`.
+    File { path: PathBuf, start_line: usize, end_line: usize },
+    /// Literal text.
+    Text(String),
+}
+
+/// Which side of its offset an inlay hint is shown on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InlayHintPosition {
+    Before,
+    After,
+}
+
+/// A label shown inline in a view, without being part of the text, such
+/// as an inferred type or a parameter name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct InlayHint {
+    pub offset: usize,
+    pub label: String,
+    pub position: InlayHintPosition,
+    /// Whether to leave a space between the label and the adjacent text.
+    #[serde(default)]
+    pub padding: bool,
+}
+
+/// A non-interactive cursor shown by a plugin, such as a collaborator's
+/// caret.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct DecorationCursor {
+    /// Identifies the cursor among those set by the same plugin.
+    pub id: String,
+    pub offset: usize,
+    pub color: Color,
+}
+
+/// A color, sent over RPC as a 32-bit ARGB value, like the colors of styles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub a: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    /// Returns an opaque color.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { a: 0xff, r, g, b }
+    }
+
+    pub fn from_argb(argb: u32) -> Self {
+        let [a, r, g, b] = argb.to_be_bytes();
+        Color { a, r, g, b }
+    }
+
+    pub fn to_argb(self) -> u32 {
+        u32::from_be_bytes([self.a, self.r, self.g, self.b])
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(self.to_argb())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        u32::deserialize(deserializer).map(Color::from_argb)
+    }
+}
+
 /// Common wrapper for plugin-originating RPCs.
 pub struct PluginCommand<T> {
     pub view_id: ViewId,
@@ -300,7 +509,17 @@ impl PluginBufferInfo {
         //TODO: do make any current assertions about paths being valid utf-8? do we want to?
         let path = path.map(|p| p.to_str().unwrap().to_owned());
         let views = views.to_owned();
-        PluginBufferInfo { buffer_id, views, rev, buf_size, nb_lines, path, syntax, config }
+        PluginBufferInfo {
+            buffer_id,
+            views,
+            rev,
+            buf_size,
+            nb_lines,
+            path,
+            syntax,
+            config,
+            named_marks: BTreeMap::new(),
+        }
     }
 }
 
@@ -395,6 +614,7 @@ mod tests {
         assert_eq!(val.rev, 1);
         assert_eq!(val.path, Some("some_path".to_owned()));
         assert_eq!(val.syntax, "toml".into());
+        assert!(val.named_marks.is_empty());
     }
 
     #[test]
@@ -408,4 +628,82 @@ mod tests {
             _ => panic!("{:?}", de.cmd),
         }
     }
+
+    #[test]
+    fn test_de_prompt() {
+        let json = r#"{"method": "prompt", "params": {"view_id": "view-id-1", "plugin_id": 42, "request_id": 3, "title": "New name", "default": "foo"}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::Prompt { request_id: 3, ref title, default: Some(ref default) }
+                if title == "New name" && default == "foo" => {}
+            _ => panic!("{:?}", de.cmd),
+        }
+
+        let response =
+            HostNotification::PromptResponse { view_id: ViewId(1), request_id: 3, result: None };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["method"], "prompt_response");
+        assert_eq!(json["params"]["request_id"], 3);
+        assert!(json["params"]["result"].is_null());
+    }
+
+    #[test]
+    fn test_de_inlay_hints() {
+        let json = r#"{"method": "set_inlay_hints", "params": {"view_id": "view-id-1", "plugin_id": 42, "hints": [{"offset": 5, "label": ": u32", "position": "after"}], "rev": 3}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::SetInlayHints { ref hints, rev: 3 } => assert_eq!(
+                hints,
+                &[InlayHint {
+                    offset: 5,
+                    label: ": u32".into(),
+                    position: InlayHintPosition::After,
+                    padding: false,
+                }]
+            ),
+            _ => panic!("{:?}", de.cmd),
+        }
+    }
+
+    #[test]
+    fn test_de_decoration_cursors() {
+        let json = r#"{"method": "set_decoration_cursor", "params": {"view_id": "view-id-1", "plugin_id": 42, "cursor": {"id": "alice", "offset": 7, "color": 4294901760}, "rev": 3}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::SetDecorationCursor { ref cursor, rev: 3 } => assert_eq!(
+                cursor,
+                &DecorationCursor { id: "alice".into(), offset: 7, color: Color::rgb(0xff, 0, 0) }
+            ),
+            _ => panic!("{:?}", de.cmd),
+        }
+        assert_eq!(json!(Color::rgb(0xff, 0, 0)), json!(0xffff_0000u32));
+
+        let json = r#"{"method": "remove_decoration_cursor", "params": {"view_id": "view-id-1", "plugin_id": 42, "id": "alice"}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::RemoveDecorationCursor { ref id } if id == "alice" => (),
+            _ => panic!("{:?}", de.cmd),
+        }
+    }
+
+    #[test]
+    fn test_de_peek() {
+        let json = r#"{"method": "show_peek", "params": {"view_id": "view-id-1", "plugin_id": 42, "anchor": 12, "content": {"file": {"path": "src/lib.rs", "start_line": 4, "end_line": 9}}}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::ShowPeek { anchor: 12, ref content } => assert_eq!(
+                content,
+                &PeekContent::File { path: "src/lib.rs".into(), start_line: 4, end_line: 9 }
+            ),
+            _ => panic!("{:?}", de.cmd),
+        }
+
+        let json =
+            r#"{"method": "hide_peek", "params": {"view_id": "view-id-1", "plugin_id": 42}}"#;
+        let de: PluginCommand<PluginNotification> = serde_json::from_str(json).unwrap();
+        match de.cmd {
+            PluginNotification::HidePeek => (),
+            _ => panic!("{:?}", de.cmd),
+        }
+    }
 }
@@ -204,6 +204,8 @@ pub enum CoreNotification {
     SaveTrace { destination: PathBuf, frontend_samples: Value },
     /// Tells `xi-core` to set the language id for the view.
     SetLanguage { view_id: ViewId, language_id: LanguageId },
+    /// Notifies `xi-core` that the specified view has received focus.
+    FocusView { view_id: ViewId },
 }
 
 /// The requests which make up the base of the protocol.
@@ -243,6 +245,13 @@ pub enum CoreRequest {
     /// Returns the view identifier that should be used to interact
     /// with the newly created view.
     NewView { file_path: Option<String> },
+    /// Tells `xi-core` to open each of the files at `file_paths`, as with
+    /// `new_view`. A file that is already open is not opened again; its
+    /// existing view is returned instead.
+    ///
+    /// Returns, for each path in order, either `{"view_id": ..}` or, if
+    /// the file could not be opened, `{"error": ..}`.
+    NewViews { file_paths: Vec<String> },
     /// Returns the current collated config object for the given view.
     GetConfig { view_id: ViewId },
     /// Returns the contents of the buffer for a given `ViewId`.
@@ -506,6 +515,20 @@ pub enum EditNotification {
         request_id: usize,
         position: Option<Position>,
     },
+    /// The user's answer to a `show_prompt`; `None` if it was cancelled.
+    PromptResponse {
+        request_id: usize,
+        result: Option<String>,
+    },
+    /// Asks the plugin running a task shown with `task_started` to stop.
+    CancelTask {
+        task_id: usize,
+    },
+    /// Files dropped onto the view at `position`, or at the caret if `None`.
+    FilesDropped {
+        paths: Vec<PathBuf>,
+        position: Option<Position>,
+    },
     SelectionIntoLines,
     DuplicateLine,
     IncreaseNumber,
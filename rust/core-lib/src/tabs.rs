@@ -20,7 +20,7 @@
 //! be renamed.
 
 use std::cell::{Cell, RefCell};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io;
@@ -41,6 +41,7 @@ use crate::editor::Editor;
 use crate::event_context::EventContext;
 use crate::file::FileManager;
 use crate::line_ending::LineEnding;
+use crate::marks::Marks;
 use crate::plugin_rpc::{PluginNotification, PluginRequest};
 use crate::plugins::rpc::ClientPluginInfo;
 use crate::plugins::{start_plugin_process, Plugin, PluginCatalog, PluginPid};
@@ -81,6 +82,7 @@ pub type BufferIdentifier = BufferId;
 pub(crate) const RENDER_VIEW_IDLE_MASK: usize = 1 << 25;
 pub(crate) const REWRAP_VIEW_IDLE_MASK: usize = 1 << 26;
 pub(crate) const FIND_VIEW_IDLE_MASK: usize = 1 << 27;
+pub(crate) const EDIT_CHUNK_VIEW_IDLE_MASK: usize = 1 << 28;
 
 const NEW_VIEW_IDLE_TOKEN: usize = 1001;
 
@@ -124,6 +126,11 @@ pub struct CoreState {
     plugins: PluginCatalog,
     // for the time being we auto-start all plugins we find on launch.
     running_plugins: Vec<Plugin>,
+    /// The view which last received focus in the frontend.
+    focused_view: Option<ViewId>,
+    /// Plugins' marks in files that have been closed, restored if the file
+    /// is opened again.
+    closed_marks: HashMap<PathBuf, Marks>,
 }
 
 /// Initial setup and bookkeeping
@@ -184,6 +191,8 @@ impl CoreState {
             id_counter: Counter::default(),
             plugins: PluginCatalog::default(),
             running_plugins: Vec::new(),
+            focused_view: None,
+            closed_marks: HashMap::new(),
         }
     }
 
@@ -331,6 +340,7 @@ impl CoreState {
             // handled at the top level
             ClientStarted { .. } => (),
             SetLanguage { view_id, language_id } => self.do_set_language(view_id, language_id),
+            FocusView { view_id } => self.do_focus_view(view_id),
         }
     }
 
@@ -339,7 +349,21 @@ impl CoreState {
         match cmd {
             //TODO: make file_path be an Option<PathBuf>
             //TODO: make this a notification
-            NewView { file_path } => self.do_new_view(file_path.map(PathBuf::from)),
+            NewView { file_path } => {
+                self.do_new_view(file_path.map(PathBuf::from)).map(|view_id| json!(view_id))
+            }
+            NewViews { file_paths } => {
+                let paths: Vec<PathBuf> = file_paths.into_iter().map(PathBuf::from).collect();
+                let results = self
+                    .open_files(&paths)
+                    .into_iter()
+                    .map(|result| match result {
+                        Ok(view_id) => json!({ "view_id": view_id }),
+                        Err(err) => json!({ "error": err }),
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!(results))
+            }
             Edit(crate::rpc::EditCommand { view_id, cmd }) => self.do_edit_sync(view_id, cmd),
             //TODO: why is this a request?? make a notification?
             GetConfig { view_id } => self.do_get_config(view_id).map(|c| json!(c)),
@@ -362,7 +386,7 @@ impl CoreState {
         }
     }
 
-    fn do_new_view(&mut self, path: Option<PathBuf>) -> Result<Value, RemoteError> {
+    fn do_new_view(&mut self, path: Option<PathBuf>) -> Result<ViewId, RemoteError> {
         let view_id = self.next_view_id();
         let buffer_id = self.next_buffer_id();
 
@@ -371,7 +395,11 @@ impl CoreState {
             None => Rope::from(""),
         };
 
-        let editor = RefCell::new(Editor::with_text(rope));
+        let mut editor = Editor::with_text(rope);
+        if let Some(marks) = path.as_ref().and_then(|p| self.closed_marks.remove(p)) {
+            editor.restore_marks(marks);
+        }
+        let editor = RefCell::new(editor);
         let view = RefCell::new(View::new(view_id, buffer_id));
 
         self.editors.insert(buffer_id, editor);
@@ -391,7 +419,26 @@ impl CoreState {
         self.pending_views.push((view_id, config));
         self.peer.schedule_idle(NEW_VIEW_IDLE_TOKEN);
 
-        Ok(json!(view_id))
+        Ok(view_id)
+    }
+
+    /// Opens a view of each of `paths`, reusing the existing view of any
+    /// file that is already open. A file that can't be opened gets an
+    /// error in its slot, without affecting the others.
+    fn open_files(&mut self, paths: &[PathBuf]) -> Vec<Result<ViewId, RemoteError>> {
+        paths
+            .iter()
+            .map(|path| match self.view_for_path(path) {
+                Some(view_id) => Ok(view_id),
+                None => self.do_new_view(Some(path.to_owned())),
+            })
+            .collect()
+    }
+
+    /// Returns a view of the file at `path`, if it is open.
+    fn view_for_path(&self, path: &Path) -> Option<ViewId> {
+        let buffer_id = self.file_manager.get_editor(path)?;
+        self.views.iter().find(|(_, v)| v.borrow().get_buffer_id() == buffer_id).map(|(id, _)| *id)
     }
 
     fn do_save<P>(&mut self, view_id: ViewId, path: P)
@@ -432,16 +479,39 @@ impl CoreState {
         let close_buffer = self.make_context(view_id).map(|ctx| ctx.close_view()).unwrap_or(true);
 
         let buffer_id = self.views.remove(&view_id).map(|v| v.borrow().get_buffer_id());
+        if self.focused_view == Some(view_id) {
+            self.focused_view = None;
+        }
 
         if let Some(buffer_id) = buffer_id {
             if close_buffer {
-                self.editors.remove(&buffer_id);
+                let marks = self.editors.remove(&buffer_id).map(|ed| ed.into_inner().take_marks());
+                let path = self.file_manager.get_info(buffer_id).map(|info| info.path.clone());
+                if let (Some(marks), Some(path)) = (marks, path) {
+                    if !marks.is_empty() {
+                        self.closed_marks.insert(path, marks);
+                    }
+                }
                 self.file_manager.close(buffer_id);
                 self.config_manager.remove_buffer(buffer_id);
             }
         }
     }
 
+    fn do_focus_view(&mut self, view_id: ViewId) {
+        if !self.views.contains_key(&view_id) {
+            return;
+        }
+        if let Some((blurred, focused)) = move_focus(&mut self.focused_view, view_id) {
+            if let Some(ctx) = blurred.and_then(|view_id| self.make_context(view_id)) {
+                ctx.view_blurred();
+            }
+            if let Some(ctx) = self.make_context(focused) {
+                ctx.view_focused();
+            }
+        }
+    }
+
     fn do_set_theme(&self, theme_name: &str) {
         //Set only if requested theme is different from the
         //current one.
@@ -572,6 +642,9 @@ impl CoreState {
             other if (other & FIND_VIEW_IDLE_MASK) != 0 => {
                 self.handle_find_callback(other ^ FIND_VIEW_IDLE_MASK)
             }
+            other if (other & EDIT_CHUNK_VIEW_IDLE_MASK) != 0 => {
+                self.handle_edit_chunk_callback(other ^ EDIT_CHUNK_VIEW_IDLE_MASK)
+            }
             other => panic!("unexpected idle token {}", other),
         };
     }
@@ -682,6 +755,14 @@ impl CoreState {
         }
     }
 
+    /// Callback for applying the next chunk of a chunked plugin edit
+    fn handle_edit_chunk_callback(&mut self, token: usize) {
+        let id: ViewId = token.into();
+        if let Some(mut ctx) = self.make_context(id) {
+            ctx.do_plugin_edit_chunk();
+        }
+    }
+
     #[cfg(feature = "notify")]
     fn handle_fs_events(&mut self) {
         let _t = trace_block("CoreState::handle_fs_events", &["core"]);
@@ -925,9 +1006,10 @@ impl CoreState {
         match plugin {
             Ok(plugin) => {
                 let init_info =
-                    self.iter_groups().map(|mut ctx| ctx.plugin_info()).collect::<Vec<_>>();
+                    self.iter_groups().map(|ctx| ctx.plugin_info(&plugin.name)).collect::<Vec<_>>();
                 plugin.initialize(init_info);
                 self.running_plugins.push(plugin);
+                self.running_plugins.sort_by(|a, b| a.invocation_key().cmp(&b.invocation_key()));
             }
             Err(e) => error!("failed to start plugin {:?}", e),
         }
@@ -954,6 +1036,19 @@ impl CoreState {
         }
     }
 
+    pub(crate) fn files_dropped(
+        &mut self,
+        view_id: ViewId,
+        paths: Vec<PathBuf>,
+        offset: usize,
+        remaining: Vec<PluginId>,
+        response: Result<Value, xi_rpc::Error>,
+    ) {
+        if let Some(mut edit_ctx) = self.make_context(view_id) {
+            edit_ctx.do_files_dropped_response(paths, offset, remaining, response);
+        }
+    }
+
     pub(crate) fn plugin_notification(
         &mut self,
         _ctx: &RpcCtx,
@@ -974,7 +1069,7 @@ impl CoreState {
         cmd: PluginRequest,
     ) -> Result<Value, RemoteError> {
         if let Some(mut edit_ctx) = self.make_context(view_id) {
-            Ok(edit_ctx.do_plugin_cmd_sync(plugin_id, cmd))
+            edit_ctx.do_plugin_cmd_sync(plugin_id, cmd)
         } else {
             Err(RemoteError::custom(404, "missing view", None))
         }
@@ -1100,11 +1195,32 @@ impl BufferId {
     }
 }
 
+/// Moves focus to `view_id`. If focus changed, returns the view that lost
+/// it, if any, and the view that gained it.
+fn move_focus(focused: &mut Option<ViewId>, view_id: ViewId) -> Option<(Option<ViewId>, ViewId)> {
+    if *focused == Some(view_id) {
+        return None;
+    }
+    let blurred = focused.replace(view_id);
+    Some((blurred, view_id))
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use serde::Deserialize;
+    use tempdir::TempDir;
+
+    use xi_rope::{Interval, RopeDelta};
+    use xi_rpc::test_utils::DummyPeer;
+    use xi_rpc::RpcPeer;
 
-    use super::ViewId;
+    use super::{move_focus, CoreState, ViewId};
+    use crate::core::dummy_weak_core;
+    use crate::plugin_rpc::{Color, DecorationCursor, PluginEdit, PluginNotification};
+    use crate::plugins::{Plugin, PluginPid};
+    use crate::rpc::EditNotification;
 
     #[test]
     fn test_deserialize_view_id() {
@@ -1114,4 +1230,122 @@ mod tests {
         let de = json!("not-a-view-id");
         assert!(ViewId::deserialize(&de).unwrap_err().is_data());
     }
+
+    #[test]
+    fn test_move_focus() {
+        let (a, b) = (ViewId(1), ViewId(2));
+        let mut focused = None;
+        assert_eq!(move_focus(&mut focused, a), Some((None, a)));
+        assert_eq!(move_focus(&mut focused, a), None);
+        assert_eq!(move_focus(&mut focused, b), Some((Some(a), b)));
+        assert_eq!(focused, Some(b));
+    }
+
+    #[test]
+    fn plugins_invoked_in_priority_order() {
+        let peer: RpcPeer = Box::new(DummyPeer);
+        let mut state = CoreState::new(&peer, None, None);
+        state.self_ref = Some(dummy_weak_core());
+        let view_id = state.do_new_view(None).unwrap();
+        state.finalize_new_views();
+        state.plugin_connect(Ok(Plugin::with_peer(peer.clone(), PluginPid(1), "low", 1)));
+        state.plugin_connect(Ok(Plugin::with_peer(peer.clone(), PluginPid(2), "high", 10)));
+        let names: Vec<_> = state.running_plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["high", "low"]);
+
+        // In dispatch order and then in reverse, each plugin replaces the same
+        // word against the same revision, with edits of equal priority.
+        // Either way, the higher-priority plugin's insert goes after the other.
+        for reversed in &[false, true] {
+            let mut ctx = state.make_context(view_id).unwrap();
+            ctx.do_edit(EditNotification::SelectAll);
+            ctx.do_edit(EditNotification::Insert { chars: "word".into() });
+            let rev = ctx.editor.borrow().get_head_rev_token();
+            let mut plugins = ctx.plugins.clone();
+            if *reversed {
+                plugins.reverse();
+            }
+            for plugin in plugins {
+                let delta =
+                    RopeDelta::simple_edit(Interval::new(0, 4), plugin.name.as_str().into(), 4);
+                let edit = PluginEdit {
+                    rev,
+                    delta,
+                    priority: 0,
+                    after_cursor: false,
+                    undo_group: None,
+                    author: plugin.name.clone(),
+                    auto_pair: false,
+                    chunk_size: None,
+                };
+                ctx.do_plugin_cmd(plugin.id, PluginNotification::Edit { edit });
+            }
+            assert_eq!(ctx.editor.borrow().get_buffer().to_string(), "lowhigh");
+        }
+    }
+
+    #[test]
+    fn decoration_cursors_dropped_with_view() {
+        let peer: RpcPeer = Box::new(DummyPeer);
+        let mut state = CoreState::new(&peer, None, None);
+        state.self_ref = Some(dummy_weak_core());
+        let closed = state.do_new_view(None).unwrap();
+        let kept = state.do_new_view(None).unwrap();
+        state.finalize_new_views();
+        state.plugin_connect(Ok(Plugin::with_peer(peer.clone(), PluginPid(1), "collab", 0)));
+
+        for &view_id in &[closed, kept] {
+            let mut ctx = state.make_context(view_id).unwrap();
+            let rev = ctx.editor.borrow().get_head_rev_token();
+            let color = Color::rgb(0, 0, 0xff);
+            let cursor = DecorationCursor { id: "alice".into(), offset: 0, color };
+            ctx.do_plugin_cmd(
+                PluginPid(1),
+                PluginNotification::SetDecorationCursor { cursor, rev },
+            );
+        }
+        assert_eq!(state.views[&closed].borrow().decoration_cursors(PluginPid(1)).len(), 1);
+
+        state.do_close_view(closed);
+        assert!(!state.views.contains_key(&closed));
+        assert!(state.make_context(closed).is_none());
+        assert_eq!(state.views[&kept].borrow().decoration_cursors(PluginPid(1)).len(), 1);
+    }
+
+    #[test]
+    fn named_marks_kept_after_close() {
+        let tmp = TempDir::new("xi-test-named-marks").unwrap();
+        let path = tmp.path().join("a.rs");
+        fs::write(&path, "hello world").unwrap();
+        let peer: RpcPeer = Box::new(DummyPeer);
+        let mut state = CoreState::new(&peer, None, None);
+        state.self_ref = Some(dummy_weak_core());
+        let view_id = state.do_new_view(Some(path.clone())).unwrap();
+        state.finalize_new_views();
+        state.plugin_connect(Ok(Plugin::with_peer(peer.clone(), PluginPid(1), "marks", 0)));
+
+        {
+            let mut ctx = state.make_context(view_id).unwrap();
+            let rev = ctx.editor.borrow().get_head_rev_token();
+            ctx.do_edit(EditNotification::Insert { chars: "oh, ".into() });
+            // set against the revision before the insert
+            for &(name, offset) in &[("a", 6), ("end", 11)] {
+                let name = name.to_owned();
+                ctx.do_plugin_cmd(
+                    PluginPid(1),
+                    PluginNotification::SetNamedMark { name, offset, rev },
+                );
+            }
+            let marks = ctx.plugin_info("marks").named_marks;
+            assert_eq!((marks["a"], marks["end"]), (10, 15));
+            assert!(ctx.plugin_info("other").named_marks.is_empty());
+        }
+        state.do_close_view(view_id);
+
+        // the file has shrunk since it was closed
+        fs::write(&path, "hello").unwrap();
+        let view_id = state.do_new_view(Some(path)).unwrap();
+        let marks = state.make_context(view_id).unwrap().plugin_info("marks").named_marks;
+        assert_eq!((marks["a"], marks["end"]), (5, 5));
+    }
 }
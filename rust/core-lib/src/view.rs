@@ -15,11 +15,13 @@
 
 use std::cell::RefCell;
 use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::iter;
 use std::ops::Range;
 
 use serde_json::Value;
 
+use crate::anchors::{AnchorStore, Anchored};
 use crate::annotations::{AnnotationStore, Annotations, ToAnnotation};
 use crate::client::{Client, Update, UpdateOp};
 use crate::edit_types::ViewEvent;
@@ -27,6 +29,7 @@ use crate::find::{Find, FindStatus};
 use crate::line_cache_shadow::{self, LineCacheShadow, RenderPlan, RenderTactic};
 use crate::linewrap::{InvalLines, Lines, VisualLine, WrapWidth};
 use crate::movement::{region_movement, selection_movement, Movement};
+use crate::plugins::rpc::{DecorationCursor, InlayHint, SearchState, Selections};
 use crate::plugins::PluginId;
 use crate::rpc::{FindQuery, GestureType, MouseAction, SelectionGranularity, SelectionModifier};
 use crate::selection::{Affinity, InsertDrift, SelRegion, Selection};
@@ -99,6 +102,29 @@ pub struct View {
 
     /// Annotations provided by plugins.
     annotations: AnnotationStore,
+
+    /// Inlay hints provided by plugins.
+    inlay_hints: AnchorStore<InlayHint>,
+
+    /// Decoration cursors placed by plugins.
+    decoration_cursors: AnchorStore<DecorationCursor>,
+
+    /// Prompts shown for plugins, by the id sent to the frontend. Each maps
+    /// to the requesting plugin and the id the plugin used for the prompt.
+    pending_prompts: HashMap<usize, (PluginId, usize)>,
+
+    /// Tracks the IDs for prompts sent to the frontend.
+    prompt_id_counter: Counter,
+
+    /// Long-running plugin tasks shown in the frontend, by the id sent to
+    /// the frontend. Each maps to the plugin and the plugin's id for it.
+    tasks: HashMap<usize, (PluginId, usize)>,
+
+    /// Tracks the IDs for tasks sent to the frontend.
+    task_id_counter: Counter,
+
+    /// The keys of the status items added by each plugin.
+    status_items: Vec<(PluginId, String)>,
 }
 
 /// Indicates what changed in the find state.
@@ -135,6 +161,18 @@ pub struct Replace {
     pub preserve_case: bool,
 }
 
+/// What `View::clear_plugin_contributions` removed.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct RemovedContributions {
+    /// Whether there were annotations, in which case the view needs to be
+    /// redrawn.
+    pub(crate) annotations: bool,
+    pub(crate) inlay_hints: bool,
+    pub(crate) decoration_cursors: bool,
+    /// The keys of the status items.
+    pub(crate) status_keys: Vec<String>,
+}
+
 /// A size, in pixel units (not display pixels).
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Size {
@@ -179,6 +217,13 @@ impl View {
             replace: None,
             replace_changed: false,
             annotations: AnnotationStore::new(),
+            inlay_hints: AnchorStore::new(),
+            decoration_cursors: AnchorStore::new(),
+            pending_prompts: HashMap::new(),
+            prompt_id_counter: Counter::default(),
+            tasks: HashMap::new(),
+            task_id_counter: Counter::default(),
+            status_items: Vec::new(),
         }
     }
 
@@ -194,6 +239,159 @@ impl View {
         self.replace.clone()
     }
 
+    /// Records a prompt requested by `plugin`, returning the id with which
+    /// it should be sent to the frontend.
+    pub(crate) fn add_pending_prompt(&mut self, plugin: PluginId, request_id: usize) -> usize {
+        let id = self.prompt_id_counter.next();
+        self.pending_prompts.insert(id, (plugin, request_id));
+        id
+    }
+
+    /// Removes the prompt with the given frontend id, returning the plugin
+    /// that requested it and the plugin's own id for it.
+    pub(crate) fn take_pending_prompt(&mut self, id: usize) -> Option<(PluginId, usize)> {
+        self.pending_prompts.remove(&id)
+    }
+
+    /// Records a task started by `plugin`, returning the id with which it
+    /// should be sent to the frontend.
+    pub(crate) fn add_task(&mut self, plugin: PluginId, task_id: usize) -> usize {
+        let id = self.task_id_counter.next();
+        self.tasks.insert(id, (plugin, task_id));
+        id
+    }
+
+    /// Returns the frontend id of the task `plugin` knows as `task_id`.
+    pub(crate) fn task_id(&self, plugin: PluginId, task_id: usize) -> Option<usize> {
+        self.tasks.iter().find(|(_, task)| **task == (plugin, task_id)).map(|(&id, _)| id)
+    }
+
+    /// Returns the plugin running the task with the given frontend id, and
+    /// the plugin's own id for it.
+    pub(crate) fn task_owner(&self, id: usize) -> Option<(PluginId, usize)> {
+        self.tasks.get(&id).cloned()
+    }
+
+    /// Forgets the task `plugin` knows as `task_id`, returning its frontend id.
+    pub(crate) fn remove_task(&mut self, plugin: PluginId, task_id: usize) -> Option<usize> {
+        let id = self.task_id(plugin, task_id)?;
+        self.tasks.remove(&id);
+        Some(id)
+    }
+
+    /// Forgets the prompts requested by `plugin`, returning their frontend ids.
+    pub(crate) fn remove_plugin_prompts(&mut self, plugin: PluginId) -> Vec<usize> {
+        remove_owned_by(&mut self.pending_prompts, plugin)
+    }
+
+    /// Forgets the tasks run by `plugin`, returning their frontend ids.
+    pub(crate) fn remove_plugin_tasks(&mut self, plugin: PluginId) -> Vec<usize> {
+        remove_owned_by(&mut self.tasks, plugin)
+    }
+
+    /// Records a status item added by `plugin`.
+    pub(crate) fn add_status_item(&mut self, plugin: PluginId, key: &str) {
+        if !self.status_items.iter().any(|(p, k)| *p == plugin && k == key) {
+            self.status_items.push((plugin, key.to_owned()));
+        }
+    }
+
+    /// Forgets a status item removed by `plugin`.
+    pub(crate) fn remove_status_item(&mut self, plugin: PluginId, key: &str) {
+        self.status_items.retain(|(p, k)| !(*p == plugin && k == key));
+    }
+
+    /// Removes the annotations, inlay hints, decoration cursors and status
+    /// items added by `plugin`, and returns what there was to remove.
+    pub(crate) fn clear_plugin_contributions(&mut self, plugin: PluginId) -> RemovedContributions {
+        let annotations = self.annotations.clear(plugin);
+        let inlay_hints = self.inlay_hints.clear(plugin);
+        let decoration_cursors = self.decoration_cursors.clear(plugin);
+        let (removed, kept): (Vec<_>, Vec<_>) =
+            self.status_items.drain(..).partition(|(p, _)| *p == plugin);
+        self.status_items = kept;
+        let status_keys = removed.into_iter().map(|(_, key)| key).collect();
+        RemovedContributions { annotations, inlay_hints, decoration_cursors, status_keys }
+    }
+
+    /// Sets the inlay hints of `plugin`, named `name`, replacing any it set
+    /// before, and sends them to the frontend.
+    pub(crate) fn set_inlay_hints(
+        &mut self,
+        text: &Rope,
+        client: &Client,
+        plugin: PluginId,
+        name: &str,
+        mut hints: Vec<InlayHint>,
+    ) {
+        hints.iter_mut().for_each(|hint| hint.offset = hint.offset.min(text.len()));
+        *self.inlay_hints.items_mut(plugin, name) = hints;
+        self.send_anchored(text, client, &self.inlay_hints, plugin);
+    }
+
+    /// Places a decoration cursor of `plugin`, named `name`, replacing the
+    /// plugin's cursor with the same id, and sends the plugin's cursors to
+    /// the frontend.
+    pub(crate) fn set_decoration_cursor(
+        &mut self,
+        text: &Rope,
+        client: &Client,
+        plugin: PluginId,
+        name: &str,
+        mut cursor: DecorationCursor,
+    ) {
+        cursor.offset = cursor.offset.min(text.len());
+        let cursors = self.decoration_cursors.items_mut(plugin, name);
+        match cursors.iter_mut().find(|c| c.id == cursor.id) {
+            Some(existing) => *existing = cursor,
+            None => cursors.push(cursor),
+        }
+        self.send_anchored(text, client, &self.decoration_cursors, plugin);
+    }
+
+    /// Removes the decoration cursor `id` of `plugin`, if it has one, and
+    /// sends the plugin's remaining cursors to the frontend.
+    pub(crate) fn remove_decoration_cursor(
+        &mut self,
+        text: &Rope,
+        client: &Client,
+        plugin: PluginId,
+        name: &str,
+        id: &str,
+    ) {
+        let cursors = self.decoration_cursors.items_mut(plugin, name);
+        let len = cursors.len();
+        cursors.retain(|c| c.id != id);
+        if cursors.len() != len {
+            self.send_anchored(text, client, &self.decoration_cursors, plugin);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn decoration_cursors(&self, plugin: PluginId) -> &[DecorationCursor] {
+        self.decoration_cursors.get(plugin).map_or(&[], |(_, cursors)| cursors)
+    }
+
+    /// Sends the frontend the items of a kind that `plugin` has placed.
+    fn send_anchored<T: Anchored>(
+        &self,
+        text: &Rope,
+        client: &Client,
+        store: &AnchorStore<T>,
+        plugin: PluginId,
+    ) {
+        if let Some((name, items)) = store.get(plugin) {
+            let items: Vec<Value> = items
+                .iter()
+                .map(|item| {
+                    let (line, col) = self.offset_to_line_col(text, item.offset());
+                    item.to_json(line, col)
+                })
+                .collect();
+            T::send(client, self.view_id, name, &items);
+        }
+    }
+
     pub(crate) fn set_has_pending_render(&mut self, pending: bool) {
         self.pending_render = pending
     }
@@ -855,6 +1053,18 @@ impl View {
             .collect::<Vec<FindStatus>>()
     }
 
+    /// Returns the state of the search started from the find bar, if any.
+    pub(crate) fn search_state(&self) -> Option<SearchState> {
+        self.find.first().and_then(|find| find.search_state(self.find_in_progress()))
+    }
+
+    /// Returns the view's selections, as reported to plugins.
+    pub(crate) fn plugin_selections(&self) -> Selections {
+        let regions: Vec<_> = self.selection.iter().map(|r| (r.start, r.end)).collect();
+        let primary = regions.len().saturating_sub(1);
+        Selections { regions, primary }
+    }
+
     /// Update front-end with any changes to view since the last time sent.
     /// The `pristine` argument indicates whether or not the buffer has
     /// unsaved changes.
@@ -1004,6 +1214,13 @@ impl View {
         let (iv, _) = delta.summary();
         self.annotations.invalidate(iv);
 
+        for plugin in self.inlay_hints.apply_delta(delta) {
+            self.send_anchored(text, client, &self.inlay_hints, plugin);
+        }
+        for plugin in self.decoration_cursors.apply_delta(delta) {
+            self.send_anchored(text, client, &self.decoration_cursors, plugin);
+        }
+
         // update only find highlights affected by change
         for find in &mut self.find {
             find.update_highlights(text, delta);
@@ -1313,6 +1530,18 @@ fn clamp(x: usize, min: usize, max: usize) -> usize {
     }
 }
 
+/// Removes the entries of a map of frontend ids to `(plugin, plugin's id)`
+/// that belong to `plugin`, returning their frontend ids in order.
+fn remove_owned_by(map: &mut HashMap<usize, (PluginId, usize)>, plugin: PluginId) -> Vec<usize> {
+    let mut ids: Vec<usize> =
+        map.iter().filter(|(_, (owner, _))| *owner == plugin).map(|(&id, _)| id).collect();
+    ids.sort();
+    for id in &ids {
+        map.remove(id);
+    }
+    ids
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1439,6 +1668,98 @@ mod tests {
         assert_eq!(view.sel_regions().len(), 1);
     }
 
+    #[test]
+    fn pending_prompts() {
+        use crate::plugins::PluginPid;
+        let mut view = View::new(1.into(), BufferId::new(2));
+        let first = view.add_pending_prompt(PluginPid(1), 7);
+        let second = view.add_pending_prompt(PluginPid(2), 7);
+        assert_ne!(first, second);
+        assert_eq!(view.take_pending_prompt(second), Some((PluginPid(2), 7)));
+        assert_eq!(view.take_pending_prompt(second), None);
+        assert_eq!(view.take_pending_prompt(first), Some((PluginPid(1), 7)));
+    }
+
+    #[test]
+    fn tasks() {
+        use crate::plugins::PluginPid;
+        let mut view = View::new(1.into(), BufferId::new(2));
+        let first = view.add_task(PluginPid(1), 0);
+        let second = view.add_task(PluginPid(2), 0);
+        assert_ne!(first, second);
+        assert_eq!(view.task_id(PluginPid(2), 0), Some(second));
+        assert_eq!(view.task_owner(first), Some((PluginPid(1), 0)));
+        assert_eq!(view.remove_task(PluginPid(1), 0), Some(first));
+        assert_eq!(view.task_owner(first), None);
+        assert_eq!(view.task_id(PluginPid(1), 0), None);
+        assert_eq!(view.remove_task(PluginPid(1), 0), None);
+        assert_eq!(view.task_id(PluginPid(2), 0), Some(second));
+
+        let third = view.add_task(PluginPid(2), 1);
+        view.add_task(PluginPid(1), 0);
+        assert_eq!(view.remove_plugin_tasks(PluginPid(2)), vec![second, third]);
+        assert_eq!(view.task_id(PluginPid(2), 1), None);
+        assert!(view.task_id(PluginPid(1), 0).is_some());
+    }
+
+    #[test]
+    fn clear_plugin_contributions() {
+        use crate::annotations::{AnnotationType, Annotations};
+        use crate::plugins::PluginPid;
+        use xi_rope::spans::SpansBuilder;
+
+        let mut view = View::new(1.into(), BufferId::new(2));
+        let text = Rope::from("hello world");
+        let all = Interval::new(0, text.len());
+        for plugin in &[PluginPid(1), PluginPid(2)] {
+            let mut sb = SpansBuilder::new(text.len());
+            sb.add_span(Interval::new(0, 5), json!("warning"));
+            let annotation_type = AnnotationType::Other("diagnostic".into());
+            let items = Annotations { items: sb.build(), annotation_type };
+            view.update_annotations(*plugin, all, items);
+        }
+        view.add_status_item(PluginPid(1), "lint");
+        view.add_status_item(PluginPid(1), "branch");
+        view.add_status_item(PluginPid(2), "lint");
+        view.add_status_item(PluginPid(1), "gone");
+        view.remove_status_item(PluginPid(1), "gone");
+
+        let removed = view.clear_plugin_contributions(PluginPid(1));
+        assert!(removed.annotations);
+        assert!(!removed.inlay_hints && !removed.decoration_cursors);
+        assert_eq!(removed.status_keys, vec!["lint".to_string(), "branch".to_string()]);
+        assert_eq!(view.annotations.iter_range(&view, &text, all).count(), 1);
+        assert_eq!(view.status_items, vec![(PluginPid(2), "lint".to_string())]);
+
+        assert_eq!(view.clear_plugin_contributions(PluginPid(1)), RemovedContributions::default());
+    }
+
+    #[test]
+    fn search_state() {
+        let mut view = View::new(1.into(), BufferId::new(2));
+        let text = Rope::from("hello hello world\n hello!");
+        assert_eq!(view.search_state(), None);
+
+        view.do_edit(
+            &text,
+            ViewEvent::Find {
+                chars: "hello".to_string(),
+                case_sensitive: true,
+                regex: false,
+                whole_words: false,
+            },
+        );
+        view.do_find(&text);
+        let state = view.search_state().unwrap();
+        assert_eq!(state.query, "hello");
+        assert!(state.case_sensitive);
+        assert!(state.in_progress);
+        assert_eq!(state.matches, vec![(0, 5), (6, 11), (19, 24)]);
+
+        view.do_find(&text);
+        assert!(!view.search_state().unwrap().in_progress);
+    }
+
     #[test]
     fn find_all() {
         let mut view = View::new(1.into(), BufferId::new(2));
@@ -131,6 +131,15 @@ impl<'a> WordCursor<'a> {
     }
 }
 
+/// Returns whether `codepoint` is part of a word, rather than whitespace
+/// or punctuation, by the same rules `WordCursor` uses.
+pub fn is_word_char(codepoint: char) -> bool {
+    match get_word_property(codepoint) {
+        WordProperty::Other => true,
+        _ => false,
+    }
+}
+
 #[derive(PartialEq, Eq)]
 enum WordBoundary {
     Interior,
@@ -92,6 +92,34 @@ fn test_state() {
     }
 }
 
+#[test]
+fn test_new_views() {
+    let mut state = XiCore::new();
+    let (tx, mut rx) = test_channel();
+    let mut rpc_looper = RpcLoop::new(tx);
+    let json = make_reader(r#"{"method":"client_started","params":{}}"#);
+    rpc_looper.mainloop(|| json, &mut state).unwrap();
+    rx.expect_rpc("available_languages");
+    rx.expect_rpc("available_themes");
+
+    // "src" is a directory, and can't be opened; the last path is already open.
+    let json = make_reader(
+        r#"{"id":0,"method":"new_views","params":{"file_paths":["../Cargo.toml","src","Cargo.toml","../Cargo.toml"]}}"#,
+    );
+    rpc_looper.mainloop(|| json, &mut state).unwrap();
+    let results = rx.expect_response().unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0], json!({"view_id": "view-id-1"}));
+    assert!(results[1]["error"].is_object());
+    assert_eq!(results[2], json!({"view_id": "view-id-5"}));
+    assert_eq!(results[3], results[0]);
+
+    let state = state.inner();
+    assert_eq!(state._test_open_views().len(), 2);
+    assert_eq!(state._test_open_editors().len(), 2);
+}
+
 #[test]
 /// Tests that the runloop exits with the correct error when receiving
 /// malformed json.
@@ -16,8 +16,9 @@
 //! and vice-versa
 
 use crate::lsp_types::*;
-use crate::types::LanguageResponseError;
+use crate::types::{LanguageResponseError, TextEditError};
 use xi_plugin_lib::{Cache, Error as PluginLibError, Hover as CoreHover, Range as CoreRange, View};
+use xi_rope::{DeltaBuilder, Rope, RopeDelta};
 
 pub(crate) fn marked_string_to_string(marked_string: &MarkedString) -> String {
     match *marked_string {
@@ -71,24 +72,28 @@ pub(crate) fn get_position_of_offset<C: Cache>(
     Ok(Position { line: line_num as u64, character: char_offset as u64 })
 }
 
-pub(crate) fn offset_of_position<C: Cache>(
-    view: &mut View<C>,
-    position: Position,
-) -> Result<usize, PluginLibError> {
-    let line_offset = view.offset_of_line(position.line as usize);
-
+/// Get the utf-8 offset in `line` of an LSP style Utf-16 based column. A
+/// column past the end of the line means the end of the line.
+fn utf8_offset_of_utf16_column(line: &str, column: usize) -> usize {
     let mut cur_len_utf16 = 0;
     let mut cur_len_utf8 = 0;
-
-    for u in view.get_line(position.line as usize)?.chars() {
-        if cur_len_utf16 >= (position.character as usize) {
+    for u in line.chars() {
+        if cur_len_utf16 >= column {
             break;
         }
         cur_len_utf16 += u.len_utf16();
         cur_len_utf8 += u.len_utf8();
     }
+    cur_len_utf8
+}
 
-    Ok(cur_len_utf8 + line_offset?)
+pub(crate) fn offset_of_position<C: Cache>(
+    view: &mut View<C>,
+    position: Position,
+) -> Result<usize, PluginLibError> {
+    let line_offset = view.offset_of_line(position.line as usize);
+    let line = view.get_line(position.line as usize)?;
+    Ok(utf8_offset_of_utf16_column(line, position.character as usize) + line_offset?)
 }
 
 pub(crate) fn core_range_from_range<C: Cache>(
@@ -113,3 +118,121 @@ pub(crate) fn core_hover_from_hover<C: Cache>(
         },
     })
 }
+
+/// Get the xi-core style utf-8 offset in `text` of an LSP style Utf-16 based
+/// position, given the offsets at which each line of `text` starts. As
+/// in LSP, a character past the end of a line means the end of the line.
+fn offset_of_position_in_text(
+    text: &str,
+    line_starts: &[usize],
+    position: Position,
+) -> Option<usize> {
+    let line_start = *line_starts.get(position.line as usize)?;
+    let line_end = line_starts.get(position.line as usize + 1).cloned().unwrap_or(text.len());
+    let line = text[line_start..line_end].trim_end_matches(|c| c == '\n' || c == '\r');
+    Some(line_start + utf8_offset_of_utf16_column(line, position.character as usize))
+}
+
+/// Converts a list of LSP `TextEdit`s, all relative to `text`, into a
+/// single delta. Edits may be given in any order, but must not overlap;
+/// inserts at the same position are applied in the order given.
+pub fn delta_from_text_edits(text: &str, edits: &[TextEdit]) -> Result<RopeDelta, TextEditError> {
+    let line_starts: Vec<usize> =
+        ::std::iter::once(0).chain(text.match_indices('\n').map(|(idx, _)| idx + 1)).collect();
+
+    let mut changes = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let start = offset_of_position_in_text(text, &line_starts, edit.range.start);
+        let end = offset_of_position_in_text(text, &line_starts, edit.range.end);
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => changes.push((start, end, edit)),
+            _ => return Err(TextEditError::OutOfBounds(edit.range)),
+        }
+    }
+    // a stable sort, so that inserts at the same position keep their order
+    changes.sort_by_key(|&(start, _, _)| start);
+
+    let mut builder = DeltaBuilder::new(text.len());
+    let mut prev: Option<(usize, &TextEdit)> = None;
+    for (start, end, edit) in changes {
+        if let Some((prev_end, prev_edit)) = prev {
+            if start < prev_end {
+                return Err(TextEditError::Overlapping(prev_edit.range, edit.range));
+            }
+        }
+        builder.replace(start..end, Rope::from(&edit.new_text));
+        prev = Some((end, edit));
+    }
+    Ok(builder.build())
+}
+
+/// Applies a list of LSP `TextEdit`s to the view's document, as a single
+/// edit.
+pub fn apply_text_edits<C: Cache>(
+    view: &mut View<C>,
+    edits: &[TextEdit],
+    priority: u64,
+    author: &str,
+) -> Result<(), TextEditError> {
+    let text = view.get_document()?;
+    let delta = delta_from_text_edits(&text, edits)?;
+    if !delta.is_identity() {
+        view.edit(delta, priority, false, true, author.to_owned());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_edit(start: (u64, u64), end: (u64, u64), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: Range {
+                start: Position { line: start.0, character: start.1 },
+                end: Position { line: end.0, character: end.1 },
+            },
+            new_text: new_text.to_owned(),
+        }
+    }
+
+    fn apply(text: &str, edits: &[TextEdit]) -> String {
+        let delta = delta_from_text_edits(text, edits).unwrap();
+        String::from(delta.apply(&Rope::from(text)))
+    }
+
+    #[test]
+    fn applies_text_edits() {
+        let text = "fn main() {\n    let x = 1;\n    let y = 2;\n}\n";
+        let edits = vec![
+            // out of order, and spanning multiple lines
+            text_edit((2, 4), (3, 0), "// gone\n"),
+            text_edit((0, 3), (0, 7), "start"),
+            text_edit((1, 8), (1, 9), "z"),
+        ];
+        assert_eq!(apply(text, &edits), "fn start() {\n    let z = 1;\n    // gone\n}\n");
+    }
+
+    #[test]
+    fn utf16_positions() {
+        // '\u{1F600}' is two utf-16 code units, and four utf-8 bytes
+        let text = "a\u{1F600}b\n";
+        let edits = vec![text_edit((0, 3), (0, 4), "c"), text_edit((0, 99), (0, 99), "!")];
+        assert_eq!(apply(text, &edits), "a\u{1F600}c!\n");
+    }
+
+    #[test]
+    fn rejects_bad_edits() {
+        let text = "one\ntwo\n";
+        let overlapping = vec![text_edit((0, 0), (1, 1), "x"), text_edit((1, 0), (1, 2), "y")];
+        match delta_from_text_edits(text, &overlapping) {
+            Err(TextEditError::Overlapping(..)) => (),
+            other => panic!("{:?}", other),
+        }
+        let out_of_bounds = vec![text_edit((5, 0), (5, 1), "x")];
+        match delta_from_text_edits(text, &out_of_bounds) {
+            Err(TextEditError::OutOfBounds(..)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+}
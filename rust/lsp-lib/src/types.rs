@@ -126,6 +126,22 @@ impl From<IOError> for Error {
     }
 }
 
+/// Errors that can occur while converting LSP `TextEdit`s into a delta
+#[derive(Debug)]
+pub enum TextEditError {
+    /// An edit's range lies outside the document
+    OutOfBounds(Range),
+    /// Two edits have overlapping ranges
+    Overlapping(Range, Range),
+    PluginLibError(PluginLibError),
+}
+
+impl From<PluginLibError> for TextEditError {
+    fn from(error: PluginLibError) -> Self {
+        TextEditError::PluginLibError(error)
+    }
+}
+
 /// Possible Errors that can occur while handling Language Plugins
 #[derive(Debug)]
 pub enum LanguageResponseError {
@@ -0,0 +1,142 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Looking up the annotations and diagnostics a plugin has sent for a
+//! position.
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::xi_core::plugin_rpc::DataSpan;
+
+/// A problem that a plugin reports in a region of the document, such as a
+/// compiler error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Diagnostic {
+    /// Returns the span to send for this diagnostic with
+    /// `View::update_annotations`, carrying its severity and message.
+    pub fn to_data_span(&self) -> DataSpan {
+        let data = json!({ "severity": self.severity, "message": self.message });
+        DataSpan { start: self.start, end: self.end, data }
+    }
+}
+
+/// Returns the annotation in `spans` that contains `offset`, if any.
+///
+/// Spans are half-open: an annotation covering `start..end` contains
+/// `start` but not `end`, so empty spans contain nothing. If several
+/// annotations overlap at `offset`, the narrowest one is returned, and of
+/// equally narrow ones, the first.
+///
+/// This is meant for mapping a click or hover onto the annotations (such as
+/// diagnostics) a plugin last sent with `View::update_annotations`.
+pub fn annotation_at(spans: &[DataSpan], offset: usize) -> Option<&DataSpan> {
+    narrowest_at(spans, offset, |span| (span.start, span.end))
+}
+
+/// Returns the diagnostic in `diagnostics` that covers `offset`, if any,
+/// under the same rules as `annotation_at`.
+pub fn diagnostic_at(diagnostics: &[Diagnostic], offset: usize) -> Option<&Diagnostic> {
+    narrowest_at(diagnostics, offset, |diag| (diag.start, diag.end))
+}
+
+/// Returns the first of the narrowest `items` whose half-open range, as
+/// given by `range`, contains `offset`.
+fn narrowest_at<T, F>(items: &[T], offset: usize, range: F) -> Option<&T>
+where
+    F: Fn(&T) -> (usize, usize),
+{
+    items
+        .iter()
+        .filter(|item| {
+            let (start, end) = range(item);
+            start <= offset && offset < end
+        })
+        .min_by_key(|item| {
+            let (start, end) = range(item);
+            end - start
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize, data: &str) -> DataSpan {
+        DataSpan { start, end, data: json!(data) }
+    }
+
+    #[test]
+    fn containment() {
+        let spans = vec![span(2, 5, "a"), span(8, 10, "b")];
+        assert_eq!(annotation_at(&spans, 3).map(|s| &s.data), Some(&json!("a")));
+        assert_eq!(annotation_at(&spans, 9).map(|s| &s.data), Some(&json!("b")));
+        assert!(annotation_at(&spans, 6).is_none());
+        assert!(annotation_at(&spans, 20).is_none());
+        assert!(annotation_at(&[], 0).is_none());
+    }
+
+    #[test]
+    fn half_open() {
+        let spans = vec![span(2, 5, "a"), span(5, 7, "b"), span(9, 9, "empty")];
+        assert!(annotation_at(&spans, 1).is_none());
+        assert_eq!(annotation_at(&spans, 2).map(|s| &s.data), Some(&json!("a")));
+        assert_eq!(annotation_at(&spans, 4).map(|s| &s.data), Some(&json!("a")));
+        assert_eq!(annotation_at(&spans, 5).map(|s| &s.data), Some(&json!("b")));
+        assert!(annotation_at(&spans, 7).is_none());
+        assert!(annotation_at(&spans, 9).is_none());
+    }
+
+    #[test]
+    fn overlapping() {
+        let spans = vec![span(0, 10, "outer"), span(3, 6, "inner"), span(3, 6, "inner2")];
+        assert_eq!(annotation_at(&spans, 4).map(|s| &s.data), Some(&json!("inner")));
+        assert_eq!(annotation_at(&spans, 8).map(|s| &s.data), Some(&json!("outer")));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let diag = |start, end, message: &str| Diagnostic {
+            start,
+            end,
+            severity: Severity::Warning,
+            message: message.into(),
+        };
+        let diags = vec![diag(0, 10, "unused fn"), diag(4, 6, "unused var"), diag(12, 12, "empty")];
+        assert_eq!(diagnostic_at(&diags, 4).map(|d| d.message.as_str()), Some("unused var"));
+        assert_eq!(diagnostic_at(&diags, 6).map(|d| d.message.as_str()), Some("unused fn"));
+        assert_eq!(diagnostic_at(&diags, 0).map(|d| d.message.as_str()), Some("unused fn"));
+        assert!(diagnostic_at(&diags, 10).is_none());
+        assert!(diagnostic_at(&diags, 12).is_none());
+
+        let span = diags[1].to_data_span();
+        assert_eq!((span.start, span.end), (4, 6));
+        assert_eq!(span.data, json!({"severity": "warning", "message": "unused var"}));
+    }
+}
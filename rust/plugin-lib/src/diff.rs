@@ -0,0 +1,133 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Computing edits from before and after snapshots of text.
+
+use xi_rope::diff::{Diff, LineHashDiff};
+use xi_rope::{DeltaBuilder, DeltaElement, Interval, Rope, RopeDelta};
+
+/// Computes a delta that turns `old` into `new`, where `old` is the text
+/// found at `base_offset` in a document of length `doc_len`.
+///
+/// The common prefix and suffix of the two strings are left untouched, and
+/// unchanged lines in between are preserved, so the resulting delta only
+/// covers the regions that actually differ. If `old == new` the delta is
+/// the identity.
+///
+/// This is useful for features such as formatting, where a plugin computes
+/// a replacement for some region of the buffer and wants to send the
+/// smallest edit it can, disturbing as little of the user's state (cursors,
+/// annotations) as possible.
+pub fn minimal_delta(old: &str, new: &str, base_offset: usize, doc_len: usize) -> RopeDelta {
+    assert!(base_offset + old.len() <= doc_len, "region extends past end of document");
+    let mut builder = DeltaBuilder::new(doc_len);
+    if old == new {
+        return builder.build();
+    }
+
+    let old_rope = Rope::from(old);
+    let new_rope = Rope::from(new);
+    let delta = LineHashDiff::compute_delta(&old_rope, &new_rope);
+
+    // `delta` is expressed against `old`; walk it, turning each run of
+    // deleted and inserted text into a replacement in the document.
+    let mut pos = 0;
+    let mut pending = String::new();
+    for el in &delta.els {
+        match *el {
+            DeltaElement::Copy(start, end) => {
+                if start > pos || !pending.is_empty() {
+                    let iv = Interval::new(base_offset + pos, base_offset + start);
+                    builder.replace(iv, Rope::from(pending.as_str()));
+                    pending.clear();
+                }
+                pos = end;
+            }
+            DeltaElement::Insert(ref text) => pending.push_str(&String::from(text)),
+        }
+    }
+    if old.len() > pos || !pending.is_empty() {
+        let iv = Interval::new(base_offset + pos, base_offset + old.len());
+        builder.replace(iv, Rope::from(pending));
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(doc: &str, delta: &RopeDelta) -> String {
+        String::from(delta.apply(&Rope::from(doc)))
+    }
+
+    #[test]
+    fn no_change() {
+        let delta = minimal_delta("hello\nworld", "hello\nworld", 0, 11);
+        assert!(delta.is_identity());
+    }
+
+    #[test]
+    fn single_char_insert() {
+        let doc = "fn main() {}";
+        let delta = minimal_delta("main()", "main(x)", 3, doc.len());
+        assert_eq!(delta.as_simple_insert().map(String::from), Some("x".to_string()));
+        assert_eq!(delta.summary(), (Interval::new(8, 8), 1));
+        assert_eq!(apply(doc, &delta), "fn main(x) {}");
+    }
+
+    #[test]
+    fn deletion() {
+        let doc = "let x = 42;";
+        let delta = minimal_delta("x = 42", "x = 4", 4, doc.len());
+        assert!(delta.is_simple_delete());
+        assert_eq!(delta.summary(), (Interval::new(9, 10), 0));
+        assert_eq!(apply(doc, &delta), "let x = 4;");
+    }
+
+    #[test]
+    fn replacement() {
+        let doc = "a\nfoo(bar)\nb\n";
+        let delta = minimal_delta("foo(bar)", "foo(baz)", 2, doc.len());
+        assert_eq!(delta.summary(), (Interval::new(8, 9), 1));
+        assert_eq!(apply(doc, &delta), "a\nfoo(baz)\nb\n");
+    }
+
+    #[test]
+    fn unchanged_lines_preserved() {
+        let long_line = "let unchanged = some_function(with, many, arguments);";
+        let old = format!("one\n{}\ntwo\n", long_line);
+        let new = format!("1\n{}\n2\n", long_line);
+        let delta = minimal_delta(&old, &new, 0, old.len());
+        assert_eq!(apply(&old, &delta), new);
+        let inserted: usize = delta
+            .els
+            .iter()
+            .map(|el| match *el {
+                DeltaElement::Insert(ref text) => text.len(),
+                DeltaElement::Copy(..) => 0,
+            })
+            .sum();
+        assert_eq!(inserted, 2);
+    }
+
+    #[test]
+    fn multibyte() {
+        let old = "caf\u{e9}";
+        let new = "caf\u{e8}";
+        let delta = minimal_delta(old, new, 0, old.len());
+        assert_eq!(delta.summary(), (Interval::new(3, 5), 2));
+        assert_eq!(apply(old, &delta), new);
+    }
+}
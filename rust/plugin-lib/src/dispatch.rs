@@ -141,6 +141,41 @@ impl<'a, P: 'a + Plugin> Dispatcher<'a, P> {
         self.plugin.get_hover(v, request_id, position)
     }
 
+    fn do_view_focused(&mut self, view_id: ViewId) {
+        let v = bail!(self.views.get_mut(&view_id), "view_focused", self.pid, view_id);
+        self.plugin.view_focused(v)
+    }
+
+    fn do_view_blurred(&mut self, view_id: ViewId) {
+        let v = bail!(self.views.get_mut(&view_id), "view_blurred", self.pid, view_id);
+        self.plugin.view_blurred(v)
+    }
+
+    fn do_prompt_response(&mut self, view_id: ViewId, request_id: usize, result: Option<String>) {
+        let v = bail!(self.views.get_mut(&view_id), "prompt_response", self.pid, view_id);
+        self.plugin.prompt_response(v, request_id, result)
+    }
+
+    fn do_cancel_task(&mut self, view_id: ViewId, task_id: usize) {
+        let v = bail!(self.views.get_mut(&view_id), "cancel_task", self.pid, view_id);
+        v.cancel_task(task_id)
+    }
+
+    fn do_files_dropped(
+        &mut self,
+        view_id: ViewId,
+        paths: Vec<PathBuf>,
+        offset: usize,
+    ) -> Result<Value, RemoteError> {
+        let v = bail_err!(self.views.get_mut(&view_id), "files_dropped", self.pid, view_id);
+        Ok(json!(self.plugin.files_dropped(v, &paths, offset)))
+    }
+
+    fn do_modified_changed(&mut self, view_id: ViewId, is_modified: bool) {
+        let v = bail!(self.views.get_mut(&view_id), "modified_changed", self.pid, view_id);
+        self.plugin.modified_changed(v, is_modified)
+    }
+
     fn do_tracing_config(&mut self, enabled: bool) {
         if enabled {
             xi_trace::enable_tracing();
@@ -167,7 +202,11 @@ impl<'a, P: 'a + Plugin> Dispatcher<'a, P> {
         } = update;
         let v = bail_err!(self.views.get_mut(&view_id), "update", self.pid, view_id);
         v.update(delta.as_ref(), new_len, new_line_count, rev, undo_group);
-        self.plugin.update(v, delta.as_ref(), edit_type, author);
+        match edit_type.as_str() {
+            "undo" => self.plugin.did_undo(v, delta.as_ref(), author),
+            "redo" => self.plugin.did_redo(v, delta.as_ref(), author),
+            _ => self.plugin.update(v, delta.as_ref(), edit_type, author),
+        }
 
         Ok(Value::from(1))
     }
@@ -208,6 +247,15 @@ impl<'a, P: Plugin> RpcHandler for Dispatcher<'a, P> {
             CustomCommand { view_id, method, params } => {
                 self.do_custom_command(view_id, &method, params)
             }
+            PromptResponse { view_id, request_id, result } => {
+                self.do_prompt_response(view_id, request_id, result)
+            }
+            ViewFocused { view_id } => self.do_view_focused(view_id),
+            ViewBlurred { view_id } => self.do_view_blurred(view_id),
+            CancelTask { view_id, task_id } => self.do_cancel_task(view_id, task_id),
+            ModifiedChanged { view_id, is_modified } => {
+                self.do_modified_changed(view_id, is_modified)
+            }
             Ping(..) => (),
         }
     }
@@ -218,6 +266,9 @@ impl<'a, P: Plugin> RpcHandler for Dispatcher<'a, P> {
         match rpc {
             Update(params) => self.do_update(params),
             CollectTrace(..) => self.do_collect_trace(),
+            FilesDropped { view_id, paths, offset } => {
+                self.do_files_dropped(view_id, paths, offset)
+            }
         }
     }
 
@@ -228,3 +279,95 @@ impl<'a, P: Plugin> RpcHandler for Dispatcher<'a, P> {
         self.plugin.idle(v);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_cache::ChunkCache;
+    use crate::test_helpers::{
+        buffer_info, make_view, run_dispatcher, test_config, RecordingPlugin,
+    };
+    use xi_rope::{Interval, RopeDelta};
+
+    fn make_update(rev: u64, delta: RopeDelta, edit_type: &str) -> PluginUpdate {
+        serde_json::from_value(json!({
+            "view_id": "view-id-1",
+            "delta": delta,
+            "new_len": delta.new_document_len(),
+            "new_line_count": 1,
+            "rev": rev,
+            "undo_group": 1,
+            "edit_type": edit_type,
+            "author": "core",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_are_distinguished() {
+        let mut plugin = RecordingPlugin::default();
+        let mut dispatcher = Dispatcher::new(&mut plugin);
+        let (view, _) = make_view::<ChunkCache>("ab");
+        dispatcher.views.insert(view.get_id(), view);
+
+        let insert = RopeDelta::simple_edit(Interval::new(2, 2), "c".into(), 2);
+        let undo = RopeDelta::simple_edit(Interval::new(2, 3), "".into(), 3);
+        let redo = RopeDelta::simple_edit(Interval::new(2, 2), "c".into(), 2);
+        dispatcher.do_update(make_update(2, insert, "insert")).unwrap();
+        dispatcher.do_update(make_update(3, undo, "undo")).unwrap();
+        dispatcher.do_update(make_update(4, redo, "redo")).unwrap();
+        // `did_redo` isn't overridden, so it falls back to `update`
+        assert_eq!(plugin.events, vec!["insert", "did_undo true", "redo"]);
+    }
+
+    #[test]
+    fn new_views_start_with_marks_from_core() {
+        // records the "end" mark of each new view, setting it if it's missing
+        let mut plugin = RecordingPlugin::on_new_view(|view| {
+            let mark = view.named_mark("end");
+            if mark.is_none() {
+                let end = view.get_buf_size();
+                view.set_named_mark("end", end);
+            }
+            format!("{:?}", mark)
+        });
+        let info = |id, path, text| buffer_info(id, path, text, test_config());
+        // core restores the marks of a file that is reopened
+        let mut reopened = info(2, "a.rs", "hello");
+        reopened["named_marks"] = json!({"end": 5});
+        run_dispatcher(
+            &mut plugin,
+            &[
+                (
+                    "initialize",
+                    json!({"plugin_id": 1, "buffer_info": [info(1, "a.rs", "hello world")]}),
+                ),
+                ("new_buffer", json!({"buffer_info": [reopened]})),
+            ],
+        );
+        assert_eq!(plugin.events, vec!["None", "Some(5)"]);
+    }
+
+    #[test]
+    fn new_views_start_with_config_from_core() {
+        let mut plugin = RecordingPlugin::on_new_view(|view| {
+            let setting = view.get_config_table().get("lint_on_save").cloned();
+            format!("{} {}", view.get_config().tab_size, setting.unwrap_or(Value::Null))
+        });
+        let mut config = test_config();
+        config["tab_size"] = json!(2);
+        // settings core doesn't know about are passed through
+        config["lint_on_save"] = json!(true);
+        let first = buffer_info(1, "a.rs", "", config.clone());
+        config["tab_size"] = json!(8);
+        let second = buffer_info(2, "b.rs", "", config);
+        run_dispatcher(
+            &mut plugin,
+            &[
+                ("initialize", json!({"plugin_id": 1, "buffer_info": [first]})),
+                ("new_buffer", json!({"buffer_info": [second]})),
+            ],
+        );
+        assert_eq!(plugin.events, vec!["2 true", "8 true"]);
+    }
+}
@@ -17,6 +17,7 @@ extern crate xi_core_lib as xi_core;
 extern crate xi_rope;
 extern crate xi_rpc;
 extern crate xi_trace;
+extern crate xi_unicode;
 #[macro_use]
 extern crate serde_json;
 extern crate bytecount;
@@ -27,14 +28,21 @@ extern crate serde;
 #[macro_use]
 extern crate log;
 
+pub mod annotations;
 mod base_cache;
 mod core_proxy;
+pub mod diff;
 mod dispatch;
+pub mod pairs;
+pub mod reflow;
 mod state_cache;
+#[cfg(test)]
+mod test_helpers;
 mod view;
+pub mod whitespace;
 
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::xi_core::plugin_rpc::{GetDataResponse, TextUnit};
 use crate::xi_core::{ConfigTable, LanguageId};
@@ -48,8 +56,10 @@ use self::dispatch::Dispatcher;
 pub use crate::base_cache::ChunkCache;
 pub use crate::core_proxy::CoreProxy;
 pub use crate::state_cache::StateCache;
-pub use crate::view::View;
-pub use crate::xi_core::plugin_rpc::{Hover, Range};
+pub use crate::view::{CompletionContext, LineInfo, TaskHandle, View};
+pub use crate::xi_core::plugin_rpc::{
+    Color, DecorationCursor, Hover, InlayHint, InlayHintPosition, MarkerId, PeekContent, Range,
+};
 
 /// Abstracts getting data from the peer. Mainly exists for mocking in tests.
 pub trait DataSource {
@@ -152,6 +162,28 @@ pub trait Plugin {
         edit_type: String,
         author: String,
     );
+    /// Called instead of `update` when the edit was an undo, with the delta
+    /// that undid it. By default this calls `update` with an `edit_type`
+    /// of `"undo"`; plugins maintaining derived state may override it to
+    /// resync rather than treat the change as a forward edit.
+    fn did_undo(
+        &mut self,
+        view: &mut View<Self::Cache>,
+        delta: Option<&RopeDelta>,
+        author: String,
+    ) {
+        self.update(view, delta, "undo".to_owned(), author)
+    }
+    /// Called instead of `update` when the edit was a redo. By default this
+    /// calls `update` with an `edit_type` of `"redo"`.
+    fn did_redo(
+        &mut self,
+        view: &mut View<Self::Cache>,
+        delta: Option<&RopeDelta>,
+        author: String,
+    ) {
+        self.update(view, delta, "redo".to_owned(), author)
+    }
     /// Called when a buffer has been saved to disk. The buffer's previous
     /// path, if one existed, is passed as `old_path`.
     fn did_save(&mut self, view: &mut View<Self::Cache>, old_path: Option<&Path>);
@@ -162,6 +194,9 @@ pub trait Plugin {
     /// Called when there is a new view that this buffer is interested in.
     /// This is called once per view, and is paired with a call to
     /// `Plugin::did_close` when the view is closed.
+    ///
+    /// The view's config (`view.get_config()`) is already the buffer's
+    /// current config; `config_changed` is only called for later changes.
     fn new_view(&mut self, view: &mut View<Self::Cache>);
 
     /// Called when a config option has changed for this view. `changes`
@@ -174,6 +209,21 @@ pub trait Plugin {
     #[allow(unused_variables)]
     fn language_changed(&mut self, view: &mut View<Self::Cache>, old_lang: LanguageId) {}
 
+    /// Called when this view receives focus in the frontend.
+    #[allow(unused_variables)]
+    fn view_focused(&mut self, view: &mut View<Self::Cache>) {}
+
+    /// Called when this view loses focus to another view. This is always
+    /// delivered before the other view's `view_focused`.
+    #[allow(unused_variables)]
+    fn view_blurred(&mut self, view: &mut View<Self::Cache>) {}
+
+    /// Called when the buffer becomes modified, or returns to its saved
+    /// state, for instance after a save or an undo. This is only delivered
+    /// on transitions, not for every edit.
+    #[allow(unused_variables)]
+    fn modified_changed(&mut self, view: &mut View<Self::Cache>, is_modified: bool) {}
+
     /// Called with a custom command.
     #[allow(unused_variables)]
     fn custom_command(&mut self, view: &mut View<Self::Cache>, method: &str, params: Value) {}
@@ -189,6 +239,31 @@ pub trait Plugin {
 
     #[allow(unused_variables)]
     fn get_hover(&mut self, view: &mut View<Self::Cache>, request_id: usize, position: usize) {}
+
+    /// Called with the user's answer to a prompt shown with `View::prompt`.
+    /// `result` is `None` if the prompt was cancelled.
+    #[allow(unused_variables)]
+    fn prompt_response(
+        &mut self,
+        view: &mut View<Self::Cache>,
+        request_id: usize,
+        result: Option<String>,
+    ) {
+    }
+
+    /// Called when the user drops files onto `view` at `offset`.
+    ///
+    /// Returning `true` consumes the drop. If no plugin consumes it, core
+    /// falls back to opening the files.
+    #[allow(unused_variables)]
+    fn files_dropped(
+        &mut self,
+        view: &mut View<Self::Cache>,
+        paths: &[PathBuf],
+        offset: usize,
+    ) -> bool {
+        false
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,152 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finding the delimiter pairs, such as brackets and quotes, around a
+//! position.
+
+/// Returns the offsets of the opening and closing delimiters of the
+/// innermost pair in `pairs` that encloses `offset` in `text`, if any.
+///
+/// A pair encloses `offset` if its opening delimiter ends at or before
+/// `offset` and its closing delimiter starts at or after it. Pairs whose
+/// delimiters are the same, such as quotes, are only matched within the
+/// line containing `offset`, and escapes are not taken into account.
+///
+/// # Panics
+///
+/// Panics if `offset` is not a char boundary of `text`.
+pub fn enclosing_pair(
+    text: &str,
+    offset: usize,
+    pairs: &[(String, String)],
+) -> Option<(usize, usize)> {
+    let brackets: Vec<_> = pairs.iter().filter(|(open, close)| open != close).collect();
+    let quotes = pairs.iter().filter(|(open, close)| open == close);
+    let bracket = enclosing_bracket(text, offset, &brackets);
+    let quote = quotes.filter_map(|(quote, _)| enclosing_quote(text, offset, quote)).max();
+    bracket.into_iter().chain(quote).max()
+}
+
+/// Finds the innermost pair of distinct delimiters around `offset`.
+fn enclosing_bracket(
+    text: &str,
+    offset: usize,
+    pairs: &[&(String, String)],
+) -> Option<(usize, usize)> {
+    // closing delimiters seen while scanning backwards, awaiting their opener
+    let mut unmatched = Vec::new();
+    let mut pos = offset;
+    while let Some((i, _)) = text[..pos].char_indices().next_back() {
+        pos = i;
+        let rest = &text[i..];
+        if let Some((_, close)) = pairs.iter().find(|(_, close)| rest.starts_with(close.as_str())) {
+            unmatched.push(close);
+        } else if let Some((open, close)) =
+            pairs.iter().find(|(open, _)| rest.starts_with(open.as_str()))
+        {
+            if unmatched.last() == Some(&close) {
+                unmatched.pop();
+            } else if unmatched.is_empty() {
+                // an opener without a closer doesn't enclose anything;
+                // keep looking further out
+                if let Some(end) = find_closer(text, offset, open, close) {
+                    return Some((i, end));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds the closing delimiter matching an opener before `from`.
+fn find_closer(text: &str, from: usize, open: &str, close: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, _) in text[from..].char_indices() {
+        let rest = &text[from + i..];
+        if rest.starts_with(close) {
+            if depth == 0 {
+                return Some(from + i);
+            }
+            depth -= 1;
+        } else if rest.starts_with(open) {
+            depth += 1;
+        }
+    }
+    None
+}
+
+/// Finds the quotes around `offset`, on its line.
+fn enclosing_quote(text: &str, offset: usize, quote: &str) -> Option<(usize, usize)> {
+    let line_start = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = text[offset..].find('\n').map(|i| offset + i).unwrap_or_else(|| text.len());
+    let before = &text[line_start..offset];
+    if before.matches(quote).count() % 2 == 0 {
+        return None;
+    }
+    let open = line_start + before.rfind(quote)?;
+    let close = offset + text[offset..line_end].find(quote)?;
+    Some((open, close))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs() -> Vec<(String, String)> {
+        vec![("\"", "\""), ("(", ")"), ("{", "}"), ("[", "]")]
+            .into_iter()
+            .map(|(open, close)| (open.to_owned(), close.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn nested() {
+        let text = "foo(bar[baz], {qux})";
+        assert_eq!(enclosing_pair(text, 9, &pairs()), Some((7, 11)));
+        assert_eq!(enclosing_pair(text, 16, &pairs()), Some((14, 18)));
+        assert_eq!(enclosing_pair(text, 13, &pairs()), Some((3, 19)));
+        // the delimiters themselves are inside the pair's bounds
+        assert_eq!(enclosing_pair(text, 8, &pairs()), Some((7, 11)));
+        assert_eq!(enclosing_pair(text, 11, &pairs()), Some((7, 11)));
+        assert_eq!(enclosing_pair(text, 12, &pairs()), Some((3, 19)));
+    }
+
+    #[test]
+    fn no_pair() {
+        let text = "foo(bar) baz";
+        assert_eq!(enclosing_pair(text, 0, &pairs()), None);
+        assert_eq!(enclosing_pair(text, 3, &pairs()), None);
+        assert_eq!(enclosing_pair(text, 10, &pairs()), None);
+        assert_eq!(enclosing_pair("", 0, &pairs()), None);
+        // unbalanced
+        assert_eq!(enclosing_pair("foo(bar", 5, &pairs()), None);
+        assert_eq!(enclosing_pair("foo)bar", 5, &pairs()), None);
+    }
+
+    #[test]
+    fn unclosed_inner() {
+        let text = "{ foo(bar }";
+        assert_eq!(enclosing_pair(text, 7, &pairs()), Some((0, 10)));
+    }
+
+    #[test]
+    fn quotes() {
+        let text = "f(\"ab\", c)\n\"d\"";
+        assert_eq!(enclosing_pair(text, 4, &pairs()), Some((2, 5)));
+        assert_eq!(enclosing_pair(text, 8, &pairs()), Some((1, 9)));
+        // quotes on other lines don't count
+        assert_eq!(enclosing_pair(text, 12, &pairs()), Some((11, 13)));
+        assert_eq!(enclosing_pair(text, 10, &pairs()), None);
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rewrapping paragraphs of prose to a column width.
+
+use xi_rope::{DeltaBuilder, RopeDelta};
+
+use crate::diff::minimal_delta;
+
+/// A line of the document: its start offset, and its text without the
+/// line ending.
+struct Line<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    fn starts_item(&self) -> bool {
+        list_marker_len(self.text.trim_start()).is_some()
+    }
+}
+
+fn lines(text: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+        let line = text[start..end].trim_end_matches('\r');
+        lines.push(Line { start, text: line });
+        if end == text.len() {
+            return lines;
+        }
+        start = end + 1;
+    }
+}
+
+/// If `s` starts with a list marker (`-`, `*`, `+`, or a number followed
+/// by `.` or `)`) and the spaces after it, returns their length.
+fn list_marker_len(s: &str) -> Option<usize> {
+    let digits = s.chars().take_while(char::is_ascii_digit).count();
+    let marker_len = match s[digits..].chars().next() {
+        Some('-') | Some('*') | Some('+') if digits == 0 => 1,
+        Some('.') | Some(')') if digits > 0 => digits + 1,
+        _ => return None,
+    };
+    let spaces = s[marker_len..].chars().take_while(|&c| c == ' ').count();
+    if spaces == 0 {
+        return None;
+    }
+    Some(marker_len + spaces)
+}
+
+/// Splits a line ending in a Markdown hard break (two spaces or a
+/// backslash) into its text and the break.
+fn split_hard_break(line: &str) -> (&str, Option<&str>) {
+    if line.ends_with("  ") {
+        (line, Some("  "))
+    } else if line.ends_with('\\') {
+        (&line[..line.len() - 1], Some("\\"))
+    } else {
+        (line, None)
+    }
+}
+
+/// Computes a delta that rewraps the paragraph containing `offset` so that
+/// its lines are at most `width` characters long, where possible. Like
+/// core's wrapping to columns, this counts every character, including a tab
+/// in the indentation, as one column.
+///
+/// A paragraph is bounded by blank lines, and each list item is a
+/// paragraph of its own. The first line's indentation and list marker are
+/// kept, and continuation lines are indented to line up with the text
+/// after them. Hard line breaks are preserved, including one that ends the
+/// paragraph. Words longer than `width` are put on a line of their own
+/// rather than broken.
+///
+/// If `offset` is on a blank line, or the paragraph is already wrapped,
+/// the delta is the identity.
+pub fn reflow_paragraph(text: &str, offset: usize, width: usize, line_ending: &str) -> RopeDelta {
+    let lines = lines(text);
+    let cur = lines.iter().rposition(|line| line.start <= offset).unwrap_or(0);
+    if lines[cur].is_blank() {
+        return DeltaBuilder::new(text.len()).build();
+    }
+    let mut first = cur;
+    while first > 0 && !lines[first].starts_item() && !lines[first - 1].is_blank() {
+        first -= 1;
+    }
+    let mut last = cur;
+    while last + 1 < lines.len() && !lines[last + 1].is_blank() && !lines[last + 1].starts_item() {
+        last += 1;
+    }
+
+    let first_line = lines[first].text;
+    let indent_len = first_line.len() - first_line.trim_start().len();
+    let marker_len = list_marker_len(&first_line[indent_len..]).unwrap_or(0);
+    let prefix = &first_line[..indent_len + marker_len];
+    let continuation =
+        if marker_len > 0 { " ".repeat(prefix.chars().count()) } else { prefix.to_owned() };
+
+    let mut wrapped = Vec::new();
+    let mut line = prefix.to_owned();
+    let mut line_len = line.chars().count();
+    let mut has_words = false;
+    for (i, source) in lines[first..=last].iter().enumerate() {
+        let source_text = if i == 0 { &source.text[prefix.len()..] } else { source.text };
+        let (source_text, hard_break) = split_hard_break(source_text);
+        for word in source_text.split_whitespace() {
+            let word_len = word.chars().count();
+            if has_words && line_len + 1 + word_len > width {
+                wrapped.push(line);
+                line = continuation.clone();
+                line_len = line.chars().count();
+                has_words = false;
+            }
+            if has_words {
+                line.push(' ');
+                line_len += 1;
+            }
+            line.push_str(word);
+            line_len += word_len;
+            has_words = true;
+        }
+        if let Some(hard_break) = hard_break {
+            line.push_str(hard_break);
+            if first + i < last {
+                wrapped.push(line);
+                line = continuation.clone();
+                line_len = line.chars().count();
+                has_words = false;
+            }
+        }
+    }
+    wrapped.push(line);
+
+    let start = lines[first].start;
+    let end = lines[last].start + lines[last].text.len();
+    minimal_delta(&text[start..end], &wrapped.join(line_ending), start, text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xi_rope::Rope;
+
+    fn reflow(doc: &str, offset: usize, width: usize) -> String {
+        let delta = reflow_paragraph(doc, offset, width, "\n");
+        String::from(delta.apply(&Rope::from(doc)))
+    }
+
+    #[test]
+    fn plain_paragraph() {
+        let doc = "one two three four five six\nseven eight\n\nnext para";
+        assert_eq!(reflow(doc, 3, 12), "one two\nthree four\nfive six\nseven eight\n\nnext para");
+        // the paragraph can be found from any of its lines
+        assert_eq!(reflow(doc, 30, 80), "one two three four five six seven eight\n\nnext para");
+        assert!(reflow_paragraph(doc, 40, 80, "\n").is_identity());
+        assert!(reflow_paragraph("one two", 0, 80, "\n").is_identity());
+    }
+
+    #[test]
+    fn indented() {
+        let doc = "    alpha beta\n    gamma delta\n";
+        assert_eq!(reflow(doc, 20, 11), "    alpha\n    beta\n    gamma\n    delta\n");
+    }
+
+    #[test]
+    fn long_words() {
+        assert_eq!(reflow("a supercalifragilistic b", 0, 5), "a\nsupercalifragilistic\nb");
+    }
+
+    #[test]
+    fn bulleted_item() {
+        let doc = "Intro:\n- alpha beta gamma delta\n- second item\n";
+        assert_eq!(reflow(doc, 10, 14), "Intro:\n- alpha beta\n  gamma delta\n- second item\n");
+        let doc = "1. alpha\n   beta gamma\n2. next\n";
+        assert_eq!(reflow(doc, 12, 80), "1. alpha beta gamma\n2. next\n");
+    }
+
+    #[test]
+    fn hard_breaks() {
+        let doc = "first line  \nsecond line\\\nthird\nfourth\n";
+        assert_eq!(reflow(doc, 0, 80), "first line  \nsecond line\\\nthird fourth\n");
+        // a break ending the paragraph is kept too
+        assert_eq!(reflow("foo\\", 0, 80), "foo\\");
+        assert_eq!(reflow("one\ntwo  \n\nnext", 0, 80), "one two  \n\nnext");
+        assert!(reflow_paragraph("foo\\", 0, 80, "\n").is_identity());
+    }
+
+    #[test]
+    fn tab_indent_is_one_column() {
+        let doc = "\talpha beta gamma";
+        assert_eq!(reflow(doc, 0, 11), "\talpha beta\n\tgamma");
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Types and helpers used for testing.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde_json::{self, Value};
+
+use crate::base_cache::ChunkCache;
+use crate::dispatch::Dispatcher;
+use crate::xi_core::plugin_rpc::{GetDataResponse, PluginBufferInfo, TextUnit};
+use crate::xi_core::{ConfigTable, PluginPid};
+use xi_rope::{LinesMetric, Rope, RopeDelta};
+use xi_rpc::test_utils::make_reader;
+use xi_rpc::{Callback, Error as RpcError, Peer, RpcLoop};
+
+use super::{Cache, Plugin, View};
+
+/// A `Peer` standing in for core: it serves `get_data` requests from a
+/// local copy of the document, and records everything else it is sent.
+#[derive(Clone, Default)]
+pub(crate) struct MockPeer {
+    text: Arc<Mutex<Rope>>,
+    sent: Arc<Mutex<Vec<(String, Value)>>>,
+    responses: Arc<Mutex<HashMap<String, Value>>>,
+}
+
+impl MockPeer {
+    pub(crate) fn new(text: &str) -> Self {
+        MockPeer { text: Arc::new(Mutex::new(Rope::from(text))), ..Default::default() }
+    }
+
+    pub(crate) fn text(&self) -> String {
+        String::from(&*self.text.lock().unwrap())
+    }
+
+    /// Sets the result returned for requests with the given method.
+    pub(crate) fn set_response(&self, method: &str, response: Value) {
+        self.responses.lock().unwrap().insert(method.to_owned(), response);
+    }
+
+    /// Returns and clears the RPCs this peer has received.
+    pub(crate) fn take_sent(&self) -> Vec<(String, Value)> {
+        self.sent.lock().unwrap().drain(..).collect()
+    }
+
+    fn get_data(&self, params: &Value) -> Result<Value, RpcError> {
+        let text = self.text.lock().unwrap();
+        let start = params["start"].as_u64().unwrap() as usize;
+        let max_size = params["max_size"].as_u64().unwrap() as usize;
+        let unit: TextUnit = serde_json::from_value(params["unit"].clone()).unwrap();
+        let offset = match unit.resolve_offset(&*text, start) {
+            Some(offset) => offset,
+            None => return Err(RpcError::InvalidResponse),
+        };
+        let mut end_off = offset.saturating_add(max_size);
+        if end_off >= text.len() {
+            end_off = text.len();
+        } else {
+            end_off = text.at_or_prev_codepoint_boundary(end_off).unwrap();
+        }
+        let first_line = text.line_of_offset(offset);
+        let first_line_offset = offset - text.offset_of_line(first_line);
+        let chunk = text.slice_to_cow(offset..end_off).into_owned();
+        Ok(json!(GetDataResponse { chunk, offset, first_line, first_line_offset }))
+    }
+}
+
+impl Peer for MockPeer {
+    fn box_clone(&self) -> Box<dyn Peer> {
+        Box::new(self.clone())
+    }
+
+    fn send_rpc_notification(&self, method: &str, params: &Value) {
+        self.sent.lock().unwrap().push((method.to_owned(), params.clone()));
+    }
+
+    fn send_rpc_request_async(&self, method: &str, params: &Value, f: Box<dyn Callback>) {
+        f.call(self.send_rpc_request(method, params))
+    }
+
+    fn send_rpc_request(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        if method == "get_data" {
+            return self.get_data(params);
+        }
+        self.sent.lock().unwrap().push((method.to_owned(), params.clone()));
+        Ok(self.responses.lock().unwrap().get(method).cloned().unwrap_or(Value::Null))
+    }
+
+    fn request_is_pending(&self) -> bool {
+        false
+    }
+
+    fn schedule_idle(&self, _token: usize) {}
+
+    fn schedule_timer(&self, _after: Instant, _token: usize) {}
+}
+
+/// A plugin that records the edits it is told about, and whatever
+/// `on_new_view` returns for each new view.
+#[derive(Default)]
+pub(crate) struct RecordingPlugin {
+    /// The edit types of updates, and the results of `on_new_view`, in the
+    /// order the plugin saw them.
+    pub(crate) events: Vec<String>,
+    on_new_view: Option<Box<dyn FnMut(&mut View<ChunkCache>) -> String>>,
+}
+
+impl RecordingPlugin {
+    /// Creates a plugin that calls `f` with each new view, and records what
+    /// it returns.
+    pub(crate) fn on_new_view<F>(f: F) -> Self
+    where
+        F: FnMut(&mut View<ChunkCache>) -> String + 'static,
+    {
+        RecordingPlugin { events: Vec::new(), on_new_view: Some(Box::new(f)) }
+    }
+}
+
+impl Plugin for RecordingPlugin {
+    type Cache = ChunkCache;
+
+    fn update(&mut self, _: &mut View<ChunkCache>, _: Option<&RopeDelta>, ty: String, _: String) {
+        self.events.push(ty);
+    }
+
+    fn did_undo(&mut self, _: &mut View<ChunkCache>, delta: Option<&RopeDelta>, _: String) {
+        self.events.push(format!("did_undo {}", delta.is_some()));
+    }
+
+    fn did_save(&mut self, _: &mut View<ChunkCache>, _: Option<&Path>) {}
+
+    fn did_close(&mut self, _: &View<ChunkCache>) {}
+
+    fn new_view(&mut self, view: &mut View<ChunkCache>) {
+        if let Some(f) = self.on_new_view.as_mut() {
+            let event = f(view);
+            self.events.push(event);
+        }
+    }
+
+    fn config_changed(&mut self, _: &mut View<ChunkCache>, _: &ConfigTable) {}
+}
+
+/// A config table containing every `BufferConfig` field.
+pub(crate) fn test_config() -> Value {
+    json!({
+        "line_ending": "\n",
+        "tab_size": 4,
+        "translate_tabs_to_spaces": true,
+        "use_tab_stops": true,
+        "font_face": "InconsolataGo",
+        "font_size": 14.0,
+        "auto_indent": true,
+        "scroll_past_end": false,
+        "wrap_width": 0,
+        "word_wrap": false,
+        "autodetect_whitespace": true,
+        "surrounding_pairs": [["\"", "\""], ["(", ")"], ["{", "}"], ["[", "]"]],
+        "save_with_newline": true,
+    })
+}
+
+/// The `PluginBufferInfo` core sends for a buffer containing `text`, as JSON.
+pub(crate) fn buffer_info(view_id: usize, path: &str, text: &str, config: Value) -> Value {
+    let nb_lines = Rope::from(text).measure::<LinesMetric>() + 1;
+    json!({
+        "buffer_id": view_id,
+        "views": [format!("view-id-{}", view_id)],
+        "rev": 1,
+        "buf_size": text.len(),
+        "nb_lines": nb_lines,
+        "path": path,
+        "syntax": "rust",
+        "config": config,
+    })
+}
+
+/// Runs `plugin` with a `Dispatcher`, as if core had sent it each of the
+/// notifications in `messages`, given as `(method, params)`.
+pub(crate) fn run_dispatcher<P: Plugin>(plugin: &mut P, messages: &[(&str, Value)]) {
+    let input = messages
+        .iter()
+        .map(|(method, params)| json!({"method": method, "params": params}).to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut dispatcher = Dispatcher::new(plugin);
+    let mut rpc_looper = RpcLoop::new(io::sink());
+    rpc_looper.mainloop(|| make_reader(input), &mut dispatcher).unwrap();
+}
+
+/// Creates a `View` of `text`, backed by a `MockPeer`.
+pub(crate) fn make_view<C: Cache>(text: &str) -> (View<C>, MockPeer) {
+    let peer = MockPeer::new(text);
+    let info: PluginBufferInfo =
+        serde_json::from_value(buffer_info(1, "test_file.rs", text, test_config())).unwrap();
+    let plugin_id: PluginPid = serde_json::from_value(json!(1)).unwrap();
+    let view = View::new(Box::new(peer.clone()), plugin_id, info);
+    (view, peer)
+}
+
+/// Applies `delta` to the peer's document and sends the matching update
+/// to `view`, as core would after an edit.
+pub(crate) fn apply_delta<C: Cache>(view: &mut View<C>, peer: &MockPeer, delta: &RopeDelta) {
+    let new_text = {
+        let mut text = peer.text.lock().unwrap();
+        *text = delta.apply(&*text);
+        text.clone()
+    };
+    let nb_lines = new_text.measure::<LinesMetric>() + 1;
+    let rev = view.rev + 1;
+    view.update(Some(delta), new_text.len(), nb_lines, rev, None);
+}
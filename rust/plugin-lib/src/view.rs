@@ -14,21 +14,66 @@
 
 use serde::Deserialize;
 use serde_json::{self, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::iter;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
 
 use crate::xi_core::plugin_rpc::{
-    GetDataResponse, PluginBufferInfo, PluginEdit, ScopeSpan, TextUnit,
+    Color, DecorationCursor, GetDataResponse, InlayHint, MarkerId, PeekContent, PluginBufferInfo,
+    PluginEdit, ScopeSpan, SearchState, Selections, TextUnit,
 };
 use crate::xi_core::{BufferConfig, ConfigTable, LanguageId, PluginPid, ViewId};
 use xi_core_lib::annotations::AnnotationType;
 use xi_core_lib::plugin_rpc::DataSpan;
+use xi_core_lib::word_boundaries::is_word_char;
 use xi_rope::interval::IntervalBounds;
-use xi_rope::RopeDelta;
+use xi_rope::{Interval, RopeDelta, Transformer};
 use xi_trace::trace_block;
+use xi_unicode::LineBreakIterator;
 
 use xi_rpc::RpcPeer;
 
-use super::{Cache, DataSource, Error};
+use super::{pairs, reflow, whitespace, Cache, DataSource, Error};
+
+/// The most lines on each side of an offset that `enclosing_pair` searches.
+const MAX_PAIR_SEARCH_LINES: usize = 1024;
+
+/// A line of a view's document, along with some information about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineInfo {
+    /// The text of the line, including its line ending.
+    pub text: String,
+    /// The offset of the start of the line.
+    pub start_offset: usize,
+    /// The offset of the end of the line, after its line ending.
+    pub end_offset: usize,
+    /// The width, in columns, of the line's leading whitespace.
+    pub indent: usize,
+    /// Whether wrapping at the configured `wrap_width` displays the line
+    /// across several visual lines. As in core, lines break at word
+    /// boundaries, and every codepoint, including a tab, is one column
+    /// wide. Always `false` when wrapping to the window width, which
+    /// plugins don't know.
+    pub is_wrapped: bool,
+}
+
+/// What a completion provider needs to know about the text around an
+/// offset, as returned by `View::completion_context`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionContext {
+    /// The part of the word being typed that comes before the offset.
+    pub prefix: String,
+    /// The text of the line containing the offset, without its line ending.
+    pub line: String,
+    /// The offsets of the whole word around the offset, which an accepted
+    /// completion should replace.
+    pub replace_range: Interval,
+    /// The punctuation character just before the word, such as the `.` in
+    /// `foo.ba`, if there is one.
+    pub trigger_char: Option<char>,
+}
 
 /// A type that acts as a proxy for a remote view. Provides access to
 /// a document cache, and implements various methods for querying and modifying
@@ -47,11 +92,37 @@ pub struct View<C> {
     buf_size: usize,
     pub(crate) view_id: ViewId,
     pub(crate) language_id: LanguageId,
+    /// Named positions in the document, set by the plugin. Offsets are
+    /// updated as the document changes.
+    named_marks: BTreeMap<String, usize>,
+    /// Positions in the document placed with `add_marker`, by id. Offsets
+    /// are updated as the document changes.
+    markers: BTreeMap<MarkerId, usize>,
+    /// The id of the next marker placed with `add_marker`.
+    next_marker_id: usize,
+    /// The id of the next prompt shown with `prompt`.
+    next_prompt_id: usize,
+    /// The cancellation flags of running tasks, by task id.
+    tasks: HashMap<usize, Weak<AtomicBool>>,
+    /// The id of the next task started with `begin_task`.
+    next_task_id: usize,
 }
 
 impl<C: Cache> View<C> {
     pub(crate) fn new(peer: RpcPeer, plugin_id: PluginPid, info: PluginBufferInfo) -> Self {
-        let PluginBufferInfo { views, rev, path, config, buf_size, nb_lines, syntax, .. } = info;
+        let PluginBufferInfo {
+            views,
+            rev,
+            path,
+            config,
+            buf_size,
+            nb_lines,
+            syntax,
+            named_marks,
+            markers: BTreeMap::new(),
+            next_marker_id: 0,
+            ..
+        } = info;
 
         assert_eq!(views.len(), 1, "assuming single view");
         let view_id = views.first().unwrap().to_owned();
@@ -68,6 +139,10 @@ impl<C: Cache> View<C> {
             undo_group: None,
             buf_size,
             language_id: syntax,
+            named_marks,
+            next_prompt_id: 0,
+            tasks: HashMap::new(),
+            next_task_id: 0,
         }
     }
 
@@ -80,11 +155,26 @@ impl<C: Cache> View<C> {
         undo_group: Option<usize>,
     ) {
         self.cache.update(delta, new_len, new_num_lines, rev);
+        self.update_marks(delta, new_len);
         self.rev = rev;
         self.undo_group = undo_group;
         self.buf_size = new_len;
     }
 
+    fn update_marks(&mut self, delta: Option<&RopeDelta>, new_len: usize) {
+        let offsets = self.named_marks.values_mut().chain(self.markers.values_mut());
+        match delta {
+            Some(delta) => {
+                let mut transformer = Transformer::new(delta);
+                for offset in offsets {
+                    *offset = transformer.transform(*offset, false);
+                }
+            }
+            // without a delta we can't track positions; keep them in bounds.
+            None => offsets.for_each(|off| *off = (*off).min(new_len)),
+        }
+    }
+
     pub(crate) fn set_language(&mut self, new_language_id: LanguageId) {
         self.language_id = new_language_id;
     }
@@ -116,6 +206,12 @@ impl<C: Cache> View<C> {
         &self.config
     }
 
+    /// Returns the view's full config table, including any settings that
+    /// are not part of `BufferConfig`.
+    pub fn get_config_table(&self) -> &ConfigTable {
+        &self.config_table
+    }
+
     pub fn get_cache(&mut self) -> &mut C {
         &mut self.cache
     }
@@ -135,6 +231,37 @@ impl<C: Cache> View<C> {
         self.cache.get_region(&ctx, interval)
     }
 
+    /// Returns at most `max_chars` characters from the start of `interval`,
+    /// along with whether the text was truncated. No more than `max_chars`
+    /// characters' worth of data is fetched, however large the interval;
+    /// the fetched text does not go through the cache.
+    pub fn get_text_range_capped<I: IntervalBounds>(
+        &mut self,
+        interval: I,
+        max_chars: usize,
+    ) -> Result<(String, bool), Error> {
+        let Interval { start, end } = interval.into_interval(self.buf_size);
+        // a char is at most four bytes
+        let max_bytes = max_chars.saturating_mul(4).min(end - start);
+        let ctx = self.make_ctx();
+        let mut text = String::new();
+        while text.len() < max_bytes {
+            let remaining = max_bytes - text.len();
+            let resp = ctx.get_data(start + text.len(), TextUnit::Utf8, remaining, self.rev)?;
+            if resp.chunk.is_empty() {
+                break;
+            }
+            text.push_str(&resp.chunk);
+        }
+
+        let mut truncated = start + text.len() < end;
+        if let Some((idx, _)) = text.char_indices().nth(max_chars) {
+            text.truncate(idx);
+            truncated = true;
+        }
+        Ok((text, truncated))
+    }
+
     pub fn get_document(&mut self) -> Result<String, Error> {
         let ctx = self.make_ctx();
         self.cache.get_document(&ctx)
@@ -150,6 +277,388 @@ impl<C: Cache> View<C> {
         self.cache.line_of_offset(&ctx, offset)
     }
 
+    /// Returns the line at `line_num` (zero-indexed), along with its
+    /// offsets, indentation, and whether it is soft-wrapped.
+    pub fn line_info(&mut self, line_num: usize) -> Result<LineInfo, Error> {
+        let start_offset = self.offset_of_line(line_num)?;
+        let text = self.get_line(line_num)?.to_owned();
+        let end_offset = start_offset + text.len();
+        let tab_size = self.config.tab_size.max(1);
+        let indent = text.chars().take_while(|&c| c == ' ' || c == '\t').fold(0, |col, c| {
+            if c == '\t' {
+                (col / tab_size + 1) * tab_size
+            } else {
+                col + 1
+            }
+        });
+        let wrap_width = self.config.wrap_width;
+        let is_wrapped =
+            !self.config.word_wrap && wrap_width > 0 && wraps_at_columns(&text, wrap_width);
+        Ok(LineInfo { text, start_offset, end_offset, indent, is_wrapped })
+    }
+
+    /// Returns the word being typed at `offset`, the line containing it,
+    /// and the character that may have triggered completion. Words are
+    /// split the way core splits them for word movement.
+    pub fn completion_context(&mut self, offset: usize) -> Result<CompletionContext, Error> {
+        let line_num = self.line_of_offset(offset)?;
+        let line_start = self.offset_of_line(line_num)?;
+        let line = self.get_line(line_num)?.trim_end_matches(|c| c == '\n' || c == '\r').to_owned();
+        let col = offset - line_start;
+        if col > line.len() || !line.is_char_boundary(col) {
+            return Err(Error::BadRequest);
+        }
+
+        let start = line[..col]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word_char(c))
+            .last()
+            .map_or(col, |(idx, _)| idx);
+        let end = col
+            + line[col..]
+                .chars()
+                .take_while(|&c| is_word_char(c))
+                .map(char::len_utf8)
+                .sum::<usize>();
+        let trigger_char =
+            line[..start].chars().next_back().filter(|&c| !is_word_char(c) && !c.is_whitespace());
+        Ok(CompletionContext {
+            prefix: line[start..col].to_owned(),
+            replace_range: Interval::new(line_start + start, line_start + end),
+            trigger_char,
+            line,
+        })
+    }
+
+    /// Returns the whitespace to insert after a newline inserted at
+    /// `offset`: the indentation of the line containing `offset`, plus one
+    /// level if the text before `offset` on that line ends with `{`.
+    ///
+    /// This is a simple, language-agnostic rule; syntax plugins may want
+    /// to compute something smarter.
+    pub fn compute_auto_indent(&mut self, offset: usize) -> Result<String, Error> {
+        let line_num = self.line_of_offset(offset)?;
+        let line_start = self.offset_of_line(line_num)?;
+        let (mut indent, opens_block) = {
+            let before = self.get_region(line_start..offset)?;
+            let indent: String = before.chars().take_while(|&c| c == ' ' || c == '\t').collect();
+            (indent, before.trim_end().ends_with('{'))
+        };
+        if opens_block {
+            if self.config.translate_tabs_to_spaces {
+                indent.extend(iter::repeat(' ').take(self.config.tab_size));
+            } else {
+                indent.push('\t');
+            }
+        }
+        Ok(indent)
+    }
+
+    /// Returns the offsets of the opening and closing delimiters of the
+    /// innermost of the configured `surrounding_pairs` that encloses
+    /// `offset`, if any. See [`pairs::enclosing_pair`] for details.
+    ///
+    /// The search starts with the lines around `offset`, and widens until
+    /// a pair is found; pairs with a delimiter more than
+    /// `MAX_PAIR_SEARCH_LINES` lines away are not found.
+    ///
+    /// [`pairs::enclosing_pair`]: pairs/fn.enclosing_pair.html
+    pub fn enclosing_pair(&mut self, offset: usize) -> Result<Option<(usize, usize)>, Error> {
+        let buf_size = self.buf_size;
+        let line = self.line_of_offset(offset)?;
+        let last_line = self.line_of_offset(buf_size)?;
+        let (brackets, quotes): (Vec<_>, Vec<_>) =
+            self.config.surrounding_pairs.iter().cloned().partition(|(open, close)| open != close);
+        let mut radius = 1;
+        loop {
+            let start = self.offset_of_line(line.saturating_sub(radius))?;
+            let end = match line + radius + 1 {
+                end_line if end_line > last_line => buf_size,
+                end_line => self.offset_of_line(end_line)?,
+            };
+            let is_whole = start == 0 && end == buf_size;
+            let text = self.get_region(start..end)?;
+            let rel_offset = offset - start;
+            if !text.is_char_boundary(rel_offset) {
+                return Err(Error::BadRequest);
+            }
+            // Quotes are only matched within a line, so the lines around
+            // `offset` are enough to find them. A bracket found in the
+            // window is innermost, but one that isn't found may be wider.
+            let bracket = pairs::enclosing_pair(text, rel_offset, &brackets);
+            if bracket.is_some() || is_whole || radius == MAX_PAIR_SEARCH_LINES {
+                let quote = pairs::enclosing_pair(text, rel_offset, &quotes);
+                let pair = bracket.into_iter().chain(quote).max();
+                return Ok(pair.map(|(open, close)| (start + open, start + close)));
+            }
+            radius = (radius * 2).min(MAX_PAIR_SEARCH_LINES);
+        }
+    }
+
+    /// Sets the mark `name` to `offset`, replacing any existing mark with
+    /// that name. Marks move with edits.
+    ///
+    /// Core keeps a copy of the marks with the buffer, so that they are
+    /// restored when its file is reopened, or when the plugin restarts.
+    /// Marks are not shared with other plugins.
+    pub fn set_named_mark(&mut self, name: &str, offset: usize) {
+        let offset = offset.min(self.buf_size);
+        self.named_marks.insert(name.to_owned(), offset);
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "name": name,
+            "offset": offset,
+            "rev": self.rev,
+        });
+        self.peer.send_rpc_notification("set_named_mark", &params);
+    }
+
+    /// Returns the current offset of the mark `name`, if it exists.
+    pub fn named_mark(&self, name: &str) -> Option<usize> {
+        self.named_marks.get(name).cloned()
+    }
+
+    /// Removes the mark `name`, returning its last offset.
+    pub fn remove_named_mark(&mut self, name: &str) -> Option<usize> {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "name": name,
+        });
+        self.peer.send_rpc_notification("remove_named_mark", &params);
+        self.named_marks.remove(name)
+    }
+
+    /// Places a marker at `offset`, returning its id. Like a named mark,
+    /// the marker moves with edits; it lasts until it is removed, or until
+    /// the view is closed.
+    pub fn add_marker(&mut self, offset: usize) -> MarkerId {
+        let id = MarkerId(self.next_marker_id);
+        self.next_marker_id += 1;
+        let offset = offset.min(self.buf_size);
+        self.markers.insert(id, offset);
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "id": id,
+            "offset": offset,
+            "rev": self.rev,
+        });
+        self.peer.send_rpc_notification("set_marker", &params);
+        id
+    }
+
+    /// Returns the current offset of the marker `id`, if it exists.
+    pub fn marker(&self, id: MarkerId) -> Option<usize> {
+        self.markers.get(&id).cloned()
+    }
+
+    /// Removes the marker `id`, returning its last offset.
+    pub fn remove_marker(&mut self, id: MarkerId) -> Option<usize> {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "id": id,
+        });
+        self.peer.send_rpc_notification("remove_marker", &params);
+        self.markers.remove(&id)
+    }
+
+    /// Returns the query and matches of the search currently active in
+    /// this view's find bar, or `None` if there is no active search.
+    pub fn active_search(&self) -> Result<Option<SearchState>, Error> {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        let result =
+            self.peer.send_rpc_request("get_search_state", &params).map_err(Error::RpcError)?;
+        Option::<SearchState>::deserialize(result).map_err(|_| Error::WrongReturnType)
+    }
+
+    /// Returns a hash of the view's file as it currently is on disk, or
+    /// `None` if the view has no file or it hasn't been saved yet. Comparing
+    /// this against an earlier value tells whether the file was changed
+    /// outside the editor. Hashes are only comparable within one run of
+    /// core, and should not be stored.
+    ///
+    /// Core doesn't hash files larger than 16 MiB, and returns an error
+    /// for them instead.
+    pub fn disk_content_hash(&self) -> Result<Option<u64>, Error> {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        let result = self
+            .peer
+            .send_rpc_request("get_disk_content_hash", &params)
+            .map_err(Error::RpcError)?;
+        Option::<u64>::deserialize(result).map_err(|_| Error::WrongReturnType)
+    }
+
+    /// Returns the view's current selections.
+    pub fn get_selections(&self) -> Result<Selections, Error> {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        let result =
+            self.peer.send_rpc_request("get_selections", &params).map_err(Error::RpcError)?;
+        Selections::deserialize(result).map_err(|_| Error::WrongReturnType)
+    }
+
+    /// Returns the number of selections in the view.
+    pub fn selection_count(&self) -> Result<usize, Error> {
+        self.get_selections().map(|sel| sel.regions.len())
+    }
+
+    /// Returns `true` if the view has more than one selection.
+    pub fn is_multi_cursor(&self) -> Result<bool, Error> {
+        self.selection_count().map(|count| count > 1)
+    }
+
+    /// Returns the index of the primary selection in the list returned by
+    /// `get_selections`.
+    pub fn primary_selection_index(&self) -> Result<usize, Error> {
+        self.get_selections().map(|sel| sel.primary)
+    }
+
+    /// Asks the frontend to prompt the user for a line of input, with the
+    /// given title and initial value. Returns an id for the request; the
+    /// answer is delivered to `Plugin::prompt_response` with the same id.
+    pub fn prompt(&mut self, title: &str, default: Option<&str>) -> usize {
+        let request_id = self.next_prompt_id;
+        self.next_prompt_id += 1;
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "request_id": request_id,
+            "title": title,
+            "default": default,
+        });
+        self.peer.send_rpc_notification("prompt", &params);
+        request_id
+    }
+
+    /// Asks the frontend to show `content` inline at `anchor_offset`,
+    /// without navigating away; for instance to peek at a definition.
+    /// Any peek already shown in this view is replaced.
+    pub fn show_peek(&self, anchor_offset: usize, content: PeekContent) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "anchor": anchor_offset,
+            "content": content,
+        });
+        self.peer.send_rpc_notification("show_peek", &params);
+    }
+
+    /// Dismisses the peek shown with `show_peek`, if any.
+    pub fn hide_peek(&self) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        self.peer.send_rpc_notification("hide_peek", &params);
+    }
+
+    /// Asks core to duplicate each of the view's selections, as a single
+    /// undoable edit. Selected text is copied immediately after itself, and
+    /// the line containing an empty selection is copied below it.
+    pub fn duplicate_selection(&mut self) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        self.peer.send_rpc_notification("duplicate_selection", &params);
+    }
+
+    /// Starts a long-running task, which the frontend shows with its
+    /// progress and lets the user cancel. Progress is reported through the
+    /// returned handle, and the task ends when the handle is dropped.
+    pub fn begin_task(&mut self, title: &str) -> TaskHandle {
+        let task_id = self.next_task_id;
+        self.next_task_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.tasks.retain(|_, flag| flag.upgrade().is_some());
+        self.tasks.insert(task_id, Arc::downgrade(&cancelled));
+
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "task_id": task_id,
+            "title": title,
+        });
+        self.peer.send_rpc_notification("begin_task", &params);
+        TaskHandle {
+            peer: self.peer.clone(),
+            plugin_id: self.plugin_id,
+            view_id: self.view_id,
+            task_id,
+            cancelled,
+        }
+    }
+
+    /// Marks a running task as cancelled by the user.
+    pub(crate) fn cancel_task(&mut self, task_id: usize) {
+        if let Some(flag) = self.tasks.get(&task_id).and_then(Weak::upgrade) {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Sets the plugin's inlay hints for this view, at offsets in the
+    /// current revision, replacing any set before; an empty list clears
+    /// them. Core moves the hints with later edits, including edits made
+    /// before these hints reach it.
+    pub fn set_inlay_hints(&self, hints: &[InlayHint]) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "hints": hints,
+            "rev": self.rev,
+        });
+        self.peer.send_rpc_notification("set_inlay_hints", &params);
+    }
+
+    /// Shows a non-interactive cursor, such as a collaborator's caret, at
+    /// `offset` in the current revision, in the given `color`. Setting a
+    /// cursor with an `id` already in use moves it. Core moves the cursors
+    /// with later edits, including edits made before this reaches it.
+    pub fn set_decoration_cursor(&self, id: &str, offset: usize, color: Color) {
+        let cursor = DecorationCursor { id: id.to_owned(), offset, color };
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "cursor": cursor,
+            "rev": self.rev,
+        });
+        self.peer.send_rpc_notification("set_decoration_cursor", &params);
+    }
+
+    /// Removes the decoration cursor `id`, if there is one.
+    pub fn remove_decoration_cursor(&self, id: &str) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "id": id,
+        });
+        self.peer.send_rpc_notification("remove_decoration_cursor", &params);
+    }
+
+    /// Removes everything this plugin has added to the view: its syntax
+    /// highlighting, annotations, inlay hints, decoration cursors and
+    /// status items. Useful when the plugin stops working on a view, such
+    /// as from `did_close`.
+    pub fn clear_all_contributions(&self) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+        });
+        self.peer.send_rpc_notification("clear_contributions", &params);
+    }
+
     pub fn add_scopes(&self, scopes: &[Vec<String>]) {
         let params = json!({
             "plugin_id": self.plugin_id,
@@ -167,8 +676,190 @@ impl<C: Cache> View<C> {
         new_undo_group: bool,
         author: String,
     ) {
+        let edit =
+            self.make_edit(delta, priority, after_cursor, new_undo_group, author, false, None);
+        self.send_edit(edit);
+    }
+
+    /// Builds an edit against the current revision, in the current undo group
+    /// unless `new_undo_group` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn make_edit(
+        &self,
+        delta: RopeDelta,
+        priority: u64,
+        after_cursor: bool,
+        new_undo_group: bool,
+        author: String,
+        auto_pair: bool,
+        chunk_size: Option<usize>,
+    ) -> PluginEdit {
         let undo_group = if new_undo_group { None } else { self.undo_group };
-        let edit = PluginEdit { rev: self.rev, delta, priority, after_cursor, undo_group, author };
+        PluginEdit {
+            rev: self.rev,
+            delta,
+            priority,
+            after_cursor,
+            undo_group,
+            author,
+            auto_pair,
+            chunk_size,
+        }
+    }
+
+    /// Sends an edit to core, like `edit`, where inserting the opening half
+    /// of a surrounding pair (such as `(`) also inserts the closing half,
+    /// with the cursor placed between them.
+    pub fn edit_auto_pair(
+        &self,
+        delta: RopeDelta,
+        priority: u64,
+        after_cursor: bool,
+        new_undo_group: bool,
+        author: String,
+    ) {
+        let edit =
+            self.make_edit(delta, priority, after_cursor, new_undo_group, author, true, None);
+        self.send_edit(edit);
+    }
+
+    /// Asks core to insert `text` at the offset of `marker`, in a new undo
+    /// group. Core inserts at where the marker is when the request reaches
+    /// it, so a series of insertions at markers placed up front lands in
+    /// the right places, even when earlier insertions shift the text later
+    /// ones are aimed at.
+    ///
+    /// Returns `Error::BadRequest` if there is no marker with that id.
+    pub fn insert_at_marker(
+        &mut self,
+        marker: MarkerId,
+        text: &str,
+        priority: u64,
+        author: String,
+    ) -> Result<(), Error> {
+        if !self.markers.contains_key(&marker) {
+            return Err(Error::BadRequest);
+        }
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "marker": marker,
+            "text": text,
+            "priority": priority,
+            "author": author,
+        });
+        self.peer.send_rpc_notification("insert_at_marker", &params);
+        Ok(())
+    }
+
+    /// Sends a single edit removing the trailing spaces and tabs from every
+    /// line of the document. Nothing is sent if there are none.
+    pub fn trim_trailing_whitespace(&mut self, priority: u64, author: String) -> Result<(), Error> {
+        let text = self.get_document()?;
+        let delta = whitespace::trim_trailing_whitespace(&text);
+        if !delta.is_identity() {
+            self.edit(delta, priority, false, true, author);
+        }
+        Ok(())
+    }
+
+    /// Sends an edit making the document end with exactly one line ending
+    /// if `ensure` is set, or with none otherwise. Nothing is sent if the
+    /// document already does.
+    pub fn ensure_final_newline(
+        &mut self,
+        ensure: bool,
+        priority: u64,
+        author: String,
+    ) -> Result<(), Error> {
+        let text = self.get_document()?;
+        let delta = whitespace::ensure_final_newline(&text, &self.config.line_ending, ensure);
+        if !delta.is_identity() {
+            self.edit(delta, priority, false, true, author);
+        }
+        Ok(())
+    }
+
+    /// Sends an edit rewrapping the paragraph around `offset` to `width`
+    /// columns. See [`reflow::reflow_paragraph`] for details. Nothing is
+    /// sent if the paragraph is already wrapped.
+    ///
+    /// [`reflow::reflow_paragraph`]: reflow/fn.reflow_paragraph.html
+    pub fn reflow_paragraph(
+        &mut self,
+        offset: usize,
+        width: usize,
+        priority: u64,
+        author: String,
+    ) -> Result<(), Error> {
+        let text = self.get_document()?;
+        let delta = reflow::reflow_paragraph(&text, offset, width, &self.config.line_ending);
+        if !delta.is_identity() {
+            self.edit(delta, priority, false, true, author);
+        }
+        Ok(())
+    }
+
+    /// Sends an edit to core, to be applied as a series of smaller edits
+    /// that each delete and insert at most `chunk_size` bytes. The edits
+    /// share one undo group. Core applies them one at a time when it is
+    /// idle, updating the frontend after each, so that it stays responsive
+    /// during very large replacements.
+    pub fn edit_chunked(
+        &self,
+        delta: RopeDelta,
+        priority: u64,
+        after_cursor: bool,
+        new_undo_group: bool,
+        author: String,
+        chunk_size: usize,
+    ) {
+        let edit = self.make_edit(
+            delta,
+            priority,
+            after_cursor,
+            new_undo_group,
+            author,
+            false,
+            Some(chunk_size),
+        );
+        self.send_edit(edit);
+    }
+
+    /// Applies `delta`, computed against the earlier revision `base_rev`,
+    /// to the current document. Changes made since `base_rev` are accounted
+    /// for, unless one of them overlaps the regions `delta` modifies, in
+    /// which case nothing is applied and an error is returned.
+    ///
+    /// This lets slow operations such as formatting keep their results when
+    /// the user has edited elsewhere in the meantime.
+    pub fn apply_rebased(
+        &mut self,
+        base_rev: u64,
+        delta: RopeDelta,
+        priority: u64,
+        author: String,
+    ) -> Result<(), Error> {
+        let edit = PluginEdit {
+            rev: base_rev,
+            delta,
+            priority,
+            after_cursor: false,
+            undo_group: None,
+            author,
+            auto_pair: false,
+            chunk_size: None,
+        };
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "edit": edit,
+        });
+        self.peer.send_rpc_request("rebased_edit", &params).map_err(Error::RpcError)?;
+        Ok(())
+    }
+
+    fn send_edit(&self, edit: PluginEdit) {
         let params = json!({
             "plugin_id": self.plugin_id,
             "view_id": self.view_id,
@@ -250,6 +941,76 @@ impl<C: Cache> View<C> {
     }
 }
 
+/// Returns `true` if core, wrapping at `width` columns, breaks `line` (with
+/// its line ending) across several visual lines. This follows core's
+/// wrapping of lines to a number of columns, where each codepoint counts
+/// as one column.
+fn wraps_at_columns(line: &str, width: usize) -> bool {
+    let mut line_width = 0;
+    let mut start = 0;
+    for (pos, hard) in LineBreakIterator::new(line) {
+        let word_width = line[start..pos].chars().count();
+        start = pos;
+        if pos == line.len() {
+            return line_width != 0 && line_width + word_width > width;
+        }
+        // a word as wide as the line gets a visual line of its own
+        if hard || (line_width == 0 && word_width >= width) {
+            return true;
+        }
+        line_width += word_width;
+        if line_width > width {
+            return true;
+        }
+    }
+    false
+}
+
+/// A long-running task started with `View::begin_task`. Dropping the handle
+/// finishes the task.
+pub struct TaskHandle {
+    peer: RpcPeer,
+    plugin_id: PluginPid,
+    view_id: ViewId,
+    task_id: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    /// Reports that the task has moved on to the step described by
+    /// `message`, with `fraction` (from 0.0 to 1.0) of the work done.
+    pub fn step(&self, message: &str, fraction: f64) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "task_id": self.task_id,
+            "message": message,
+            "fraction": fraction,
+        });
+        self.peer.send_rpc_notification("update_task", &params);
+    }
+
+    /// Returns `true` if the user has asked for the task to be cancelled.
+    /// The task is expected to check this between steps and stop early.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Finishes the task. This is the same as dropping the handle.
+    pub fn finish(self) {}
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        let params = json!({
+            "plugin_id": self.plugin_id,
+            "view_id": self.view_id,
+            "task_id": self.task_id,
+        });
+        self.peer.send_rpc_notification("finish_task", &params);
+    }
+}
+
 /// A simple wrapper type that acts as a `DataSource`.
 pub struct FetchCtx {
     plugin_id: PluginPid,
@@ -278,3 +1039,440 @@ impl DataSource for FetchCtx {
         GetDataResponse::deserialize(result).map_err(|_| Error::WrongReturnType)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_cache::ChunkCache;
+    use crate::test_helpers::{apply_delta, buffer_info, make_view, test_config, MockPeer};
+    use crate::xi_core::plugin_rpc::InlayHintPosition;
+    use xi_core_lib::word_boundaries::WordCursor;
+    use xi_rope::{Delta, Interval, Rope};
+
+    #[test]
+    fn named_mark_moves_with_edits() {
+        let (mut view, peer) = make_view::<ChunkCache>("hello world");
+        view.set_named_mark("a", 6);
+        view.set_named_mark("b", 2);
+
+        let d = Delta::simple_edit(Interval::new(0, 0), "why ".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        assert_eq!(view.named_mark("a"), Some(10));
+        assert_eq!(view.named_mark("b"), Some(6));
+
+        // deleting the text around a mark moves it to the start of the deletion
+        let d = Delta::simple_edit(Interval::new(8, 12), "".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        assert_eq!(peer.text(), "why hellrld");
+        assert_eq!(view.named_mark("a"), Some(8));
+        assert_eq!(view.named_mark("b"), Some(6));
+        assert_eq!(view.named_mark("c"), None);
+    }
+
+    #[test]
+    fn named_marks_restored_after_reopen() {
+        let (mut view, peer) = make_view::<ChunkCache>("hello world");
+        view.set_named_mark("a", 6);
+        view.set_named_mark("end", 11);
+        // core keeps a copy of the marks...
+        let sent = peer.take_sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].0, "set_named_mark");
+        assert_eq!(sent[1].1["name"], json!("end"));
+        assert_eq!(sent[1].1["offset"], json!(11));
+        assert_eq!(sent[1].1["rev"], json!(view.rev));
+
+        // ...and sends them with the buffer when its file is reopened,
+        // moved back into the text if it has shrunk.
+        let mut info = buffer_info(1, "test_file.rs", "hello", test_config());
+        info["named_marks"] = json!({"a": 5, "end": 5});
+        let info = serde_json::from_value(info).unwrap();
+        let reopened =
+            View::<ChunkCache>::new(Box::new(MockPeer::new("hello")), view.plugin_id, info);
+        assert_eq!(reopened.named_mark("a"), Some(5));
+        assert_eq!(reopened.named_mark("end"), Some(5));
+    }
+
+    #[test]
+    fn line_info() {
+        let (mut view, _peer) = make_view::<ChunkCache>("fn main() {\n\t  let x = 1;\n}\n");
+        let info = view.line_info(1).unwrap();
+        assert_eq!(info.text, view.get_line(1).unwrap());
+        assert_eq!(info.start_offset, view.offset_of_line(1).unwrap());
+        assert_eq!(info.end_offset, view.offset_of_line(2).unwrap());
+        assert_eq!(info.indent, 6);
+        assert!(!info.is_wrapped);
+
+        view.config.wrap_width = 8;
+        assert!(view.line_info(1).unwrap().is_wrapped);
+        assert!(!view.line_info(2).unwrap().is_wrapped);
+        assert_eq!(view.line_info(0).unwrap().indent, 0);
+
+        // a tab size of zero doesn't panic
+        view.config.tab_size = 0;
+        assert_eq!(view.line_info(1).unwrap().indent, 3);
+    }
+
+    #[test]
+    fn column_wrapping() {
+        assert!(!wraps_at_columns("", 4));
+        assert!(!wraps_at_columns("abc de\n", 8));
+        assert!(wraps_at_columns("abc defgh\n", 8));
+        // columns are codepoints, not bytes
+        assert!(!wraps_at_columns("\u{e9}\u{e9}\u{e9}\u{e9} \u{e9}\u{e9}\n", 8));
+        // lines only break between words
+        assert!(!wraps_at_columns("abcdefghijkl\n", 8));
+        assert!(wraps_at_columns("abcdefghijkl mn", 8));
+        // the last line of a document has no line ending
+        assert!(!wraps_at_columns("abc defg", 8));
+    }
+
+    #[test]
+    fn prompt() {
+        let (mut view, peer) = make_view::<ChunkCache>("fn foo() {}");
+        let first = view.prompt("Rename symbol", Some("foo"));
+        let second = view.prompt("Rename symbol", None);
+        assert_ne!(first, second);
+
+        let sent = peer.take_sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].0, "prompt");
+        assert_eq!(sent[0].1["request_id"], json!(first));
+        assert_eq!(sent[0].1["title"], json!("Rename symbol"));
+        assert_eq!(sent[0].1["default"], json!("foo"));
+        assert!(sent[1].1["default"].is_null());
+    }
+
+    #[test]
+    fn peek() {
+        let (view, peer) = make_view::<ChunkCache>("let x = foo();");
+        let content = PeekContent::File { path: "src/foo.rs".into(), start_line: 10, end_line: 14 };
+        view.show_peek(8, content.clone());
+        view.show_peek(8, PeekContent::Text("fn foo() -> u32".into()));
+        view.hide_peek();
+
+        let sent = peer.take_sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].0, "show_peek");
+        assert_eq!(sent[0].1["anchor"], json!(8));
+        assert_eq!(
+            serde_json::from_value::<PeekContent>(sent[0].1["content"].clone()).unwrap(),
+            content
+        );
+        assert_eq!(sent[1].1["content"], json!({"text": "fn foo() -> u32"}));
+        assert_eq!(sent[2].0, "hide_peek");
+        assert_eq!(sent[2].1["view_id"], sent[0].1["view_id"]);
+    }
+
+    #[test]
+    fn enclosing_pair() {
+        let (mut view, _) = make_view::<ChunkCache>("if (a[0] == b) {}");
+        assert_eq!(view.enclosing_pair(6).unwrap(), Some((5, 7)));
+        assert_eq!(view.enclosing_pair(10).unwrap(), Some((3, 13)));
+        assert_eq!(view.enclosing_pair(16).unwrap(), Some((15, 16)));
+        assert_eq!(view.enclosing_pair(1).unwrap(), None);
+        assert!(view.enclosing_pair(40).is_err());
+
+        // the search widens to pairs spanning many lines, up to a limit
+        let text = format!("{{\n{}x\n{}}}", "\n".repeat(100), "\n".repeat(100));
+        let (mut view, _) = make_view::<ChunkCache>(&text);
+        assert_eq!(view.enclosing_pair(102).unwrap(), Some((0, text.len() - 1)));
+        let text = format!("{{{}x{}}}", "\n".repeat(1100), "\n".repeat(1100));
+        let (mut view, _) = make_view::<ChunkCache>(&text);
+        assert_eq!(view.enclosing_pair(1101).unwrap(), None);
+    }
+
+    #[test]
+    fn completion_context() {
+        let (mut view, _) = make_view::<ChunkCache>("let x = 1;\nself.foo.ba_r()\n");
+        // right after the dot
+        let ctx = view.completion_context(20).unwrap();
+        assert_eq!(ctx.prefix, "");
+        assert_eq!(ctx.line, "self.foo.ba_r()");
+        assert_eq!(ctx.trigger_char, Some('.'));
+        assert_eq!(ctx.replace_range, Interval::new(20, 24));
+
+        // in the middle of a word
+        let ctx = view.completion_context(22).unwrap();
+        assert_eq!(ctx.prefix, "ba");
+        assert_eq!(ctx.trigger_char, Some('.'));
+        assert_eq!(ctx.replace_range, Interval::new(20, 24));
+
+        // after whitespace there is no trigger
+        let ctx = view.completion_context(5).unwrap();
+        assert_eq!(ctx.prefix, "x");
+        assert_eq!(ctx.line, "let x = 1;");
+        assert_eq!(ctx.trigger_char, None);
+        assert_eq!(ctx.replace_range, Interval::new(4, 5));
+
+        // words end where core's word movement stops
+        let line = "x.«naïve_1»$";
+        let (mut view, _) = make_view::<ChunkCache>(line);
+        let ctx = view.completion_context(line.len() - 1).unwrap();
+        let start = WordCursor::new(&Rope::from(line), line.len() - 1).prev_boundary().unwrap();
+        assert_eq!(ctx.replace_range, Interval::new(start, line.len() - 1));
+        assert_eq!(ctx.trigger_char, Some('.'));
+    }
+
+    #[test]
+    fn compute_auto_indent() {
+        let text = "fn main() {\n    if x {  \n    let y = 1;\n}";
+        let (mut view, _) = make_view::<ChunkCache>(text);
+        // after a line ending in a brace, indent one level more
+        assert_eq!(view.compute_auto_indent(11).unwrap(), "    ");
+        assert_eq!(view.compute_auto_indent(24).unwrap(), "        ");
+        // after a plain line, match its indent
+        assert_eq!(view.compute_auto_indent(39).unwrap(), "    ");
+        // splitting the brace's line before the brace
+        assert_eq!(view.compute_auto_indent(10).unwrap(), "");
+    }
+
+    #[test]
+    fn decoration_cursors() {
+        let (mut view, peer) = make_view::<ChunkCache>("hello world");
+        let d = Delta::simple_edit(Interval::new(0, 0), "oh ".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        view.set_decoration_cursor("alice", 9, Color::rgb(0xff, 0, 0));
+        let sent = peer.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, "set_decoration_cursor");
+        assert_eq!(
+            sent[0].1,
+            json!({
+                "plugin_id": view.plugin_id,
+                "view_id": "view-id-1",
+                "cursor": {"id": "alice", "offset": 9, "color": 0xffff_0000u32},
+                // the cursor is placed in the revision the plugin last saw
+                "rev": view.rev,
+            })
+        );
+
+        view.remove_decoration_cursor("alice");
+        let sent = peer.take_sent();
+        assert_eq!(sent[0].0, "remove_decoration_cursor");
+        assert_eq!(sent[0].1["id"], json!("alice"));
+    }
+
+    #[test]
+    fn clear_all_contributions() {
+        let (view, peer) = make_view::<ChunkCache>("let x = 1;");
+        let hint = InlayHint {
+            offset: 5,
+            label: ": u32".into(),
+            position: InlayHintPosition::After,
+            padding: false,
+        };
+        view.set_inlay_hints(&[hint]);
+        view.add_status_item("lint", "ok", "left");
+        peer.take_sent();
+
+        view.clear_all_contributions();
+        let sent = peer.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, "clear_contributions");
+        assert_eq!(sent[0].1["view_id"], json!("view-id-1"));
+    }
+
+    #[test]
+    fn inlay_hints() {
+        let (mut view, peer) = make_view::<ChunkCache>("let x = foo(1);");
+        let hint = |offset, label: &str, position| InlayHint {
+            offset,
+            label: label.into(),
+            position,
+            padding: false,
+        };
+        let d = Delta::simple_edit(Interval::new(0, 0), "mut ".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        view.set_inlay_hints(&[
+            hint(9, ": u32", InlayHintPosition::After),
+            hint(16, "count:", InlayHintPosition::Before),
+        ]);
+        let sent = peer.take_sent();
+        assert_eq!(sent[0].0, "set_inlay_hints");
+        // the hints are placed in the revision the plugin last saw
+        assert_eq!(sent[0].1["rev"], json!(view.rev));
+        assert_eq!(sent[0].1["hints"][0]["offset"], json!(9));
+        assert_eq!(sent[0].1["hints"][0]["position"], json!("after"));
+        assert_eq!(sent[0].1["hints"][1]["position"], json!("before"));
+
+        // core moves them with edits, so they aren't resent
+        let d = Delta::simple_edit(Interval::new(0, 0), "// \n".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        assert!(peer.take_sent().is_empty());
+
+        view.set_inlay_hints(&[]);
+        assert_eq!(peer.take_sent()[0].1["hints"], json!([]));
+    }
+
+    #[test]
+    fn text_range_capped() {
+        let text = "h\u{e9}llo w\u{f6}rld";
+        let (mut view, _) = make_view::<ChunkCache>(text);
+        assert_eq!(view.get_text_range_capped(.., 20).unwrap(), (text.to_owned(), false));
+        assert_eq!(view.get_text_range_capped(.., 11).unwrap(), (text.to_owned(), false));
+        assert_eq!(
+            view.get_text_range_capped(.., 10).unwrap(),
+            ("h\u{e9}llo w\u{f6}rl".to_owned(), true)
+        );
+        assert_eq!(view.get_text_range_capped(0..3, 2).unwrap(), ("h\u{e9}".to_owned(), false));
+        assert_eq!(view.get_text_range_capped(0..3, 1).unwrap(), ("h".to_owned(), true));
+        assert_eq!(view.get_text_range_capped(1..5, 2).unwrap(), ("\u{e9}l".to_owned(), true));
+        assert_eq!(view.get_text_range_capped(7.., 0).unwrap(), (String::new(), true));
+        assert_eq!(view.get_text_range_capped(3..3, 5).unwrap(), (String::new(), false));
+    }
+
+    #[test]
+    fn tasks() {
+        let (mut view, peer) = make_view::<ChunkCache>("");
+        let task = view.begin_task("Reindexing");
+        task.step("Scanning", 0.25);
+        task.step("Indexing", 0.5);
+        assert!(!task.is_cancelled());
+        view.cancel_task(task.task_id);
+        assert!(task.is_cancelled());
+        task.finish();
+
+        // dropping an unfinished handle finishes the task
+        let other = view.begin_task("Other");
+        assert_ne!(other.task_id, 0);
+        drop(other);
+        view.cancel_task(1);
+
+        let sent = peer.take_sent();
+        let methods: Vec<&str> = sent.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "begin_task",
+                "update_task",
+                "update_task",
+                "finish_task",
+                "begin_task",
+                "finish_task"
+            ]
+        );
+        assert_eq!(sent[0].1["title"], json!("Reindexing"));
+        assert_eq!(sent[1].1["message"], json!("Scanning"));
+        assert_eq!(sent[2].1["fraction"], json!(0.5));
+        assert!(sent[..4].iter().all(|(_, params)| params["task_id"] == json!(0)));
+        assert_eq!(sent[5].1["task_id"], json!(1));
+    }
+
+    #[test]
+    fn active_search() {
+        let (view, peer) = make_view::<ChunkCache>("hello world, hello");
+        assert_eq!(view.active_search().unwrap(), None);
+
+        peer.set_response(
+            "get_search_state",
+            json!({
+                "query": "hello",
+                "case_sensitive": false,
+                "is_regex": false,
+                "whole_words": false,
+                "matches": [[0, 5], [13, 18]],
+                "in_progress": false,
+            }),
+        );
+        let state = view.active_search().unwrap().unwrap();
+        assert_eq!(state.query, "hello");
+        assert_eq!(state.matches.len(), 2);
+
+        let (method, params) = peer.take_sent().pop().unwrap();
+        assert_eq!(method, "get_search_state");
+        assert_eq!(params["view_id"], json!("view-id-1"));
+    }
+
+    #[test]
+    fn disk_content_hash() {
+        let (view, peer) = make_view::<ChunkCache>("hello");
+        assert_eq!(view.disk_content_hash().unwrap(), None);
+        peer.set_response("get_disk_content_hash", json!(42));
+        assert_eq!(view.disk_content_hash().unwrap(), Some(42));
+        peer.set_response("get_disk_content_hash", json!("nope"));
+        assert!(view.disk_content_hash().is_err());
+    }
+
+    #[test]
+    fn selections() {
+        let (view, peer) = make_view::<ChunkCache>("one\ntwo\nthree");
+        peer.set_response(
+            "get_selections",
+            json!({"regions": [[0, 0], [4, 4], [8, 8]], "primary": 2}),
+        );
+        assert_eq!(view.selection_count().unwrap(), 3);
+        assert!(view.is_multi_cursor().unwrap());
+        assert_eq!(view.primary_selection_index().unwrap(), 2);
+
+        peer.set_response("get_selections", json!({"regions": [[0, 3]], "primary": 0}));
+        assert_eq!(view.selection_count().unwrap(), 1);
+        assert!(!view.is_multi_cursor().unwrap());
+
+        let (method, params) = peer.take_sent().pop().unwrap();
+        assert_eq!(method, "get_selections");
+        assert_eq!(params["view_id"], json!("view-id-1"));
+    }
+
+    #[test]
+    fn whitespace_cleanup() {
+        let (mut view, peer) = make_view::<ChunkCache>("a  \nb\t\nc\n\n");
+        let last_edit = |peer: &MockPeer| {
+            let (method, params) = peer.take_sent().pop().unwrap();
+            assert_eq!(method, "edit");
+            serde_json::from_value::<PluginEdit>(params["edit"].clone()).unwrap()
+        };
+
+        view.trim_trailing_whitespace(0, "test".into()).unwrap();
+        let edit = last_edit(&peer);
+        assert_eq!(edit.undo_group, None);
+        apply_delta(&mut view, &peer, &edit.delta);
+        assert_eq!(peer.text(), "a\nb\nc\n\n");
+
+        view.ensure_final_newline(true, 0, "test".into()).unwrap();
+        apply_delta(&mut view, &peer, &last_edit(&peer).delta);
+        assert_eq!(peer.text(), "a\nb\nc\n");
+
+        // nothing left to fix
+        view.trim_trailing_whitespace(0, "test".into()).unwrap();
+        view.ensure_final_newline(true, 0, "test".into()).unwrap();
+        assert!(peer.take_sent().is_empty());
+    }
+
+    #[test]
+    fn reflow_paragraph() {
+        let (mut view, peer) = make_view::<ChunkCache>("# Title\n\nsome words to\nwrap\n");
+        view.reflow_paragraph(12, 80, 0, "test".into()).unwrap();
+        let (method, params) = peer.take_sent().pop().unwrap();
+        assert_eq!(method, "edit");
+        let edit: PluginEdit = serde_json::from_value(params["edit"].clone()).unwrap();
+        apply_delta(&mut view, &peer, &edit.delta);
+        assert_eq!(peer.text(), "# Title\n\nsome words to wrap\n");
+
+        view.reflow_paragraph(12, 80, 0, "test".into()).unwrap();
+        assert!(peer.take_sent().is_empty());
+    }
+
+    #[test]
+    fn insert_at_marker() {
+        let (mut view, peer) = make_view::<ChunkCache>("fn f(a) {}");
+        let args = view.add_marker(6);
+        let body = view.add_marker(9);
+        assert_ne!(args, body);
+        let d = Delta::simple_edit(Interval::new(6, 6), ", b".into(), view.get_buf_size());
+        apply_delta(&mut view, &peer, &d);
+        assert_eq!(view.marker(body), Some(12));
+        peer.take_sent();
+
+        // core finds the marker's offset, so the edit doesn't depend on the
+        // revision the plugin has seen
+        view.insert_at_marker(body, " a + b ", 0, "test".into()).unwrap();
+        let (method, params) = peer.take_sent().pop().unwrap();
+        assert_eq!(method, "insert_at_marker");
+        assert_eq!(params["marker"], json!(body));
+        assert_eq!(params["text"], json!(" a + b "));
+
+        assert_eq!(view.remove_marker(body), Some(12));
+        assert!(view.insert_at_marker(body, "x", 0, "test".into()).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2018 The xi-editor Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Computing edits that clean up whitespace.
+
+use xi_rope::{DeltaBuilder, Interval, Rope, RopeDelta};
+
+fn is_line_ending(c: char) -> bool {
+    c == '\n' || c == '\r'
+}
+
+/// Computes a delta that removes the spaces and tabs at the end of each
+/// line of `text`. Lines without trailing whitespace are left untouched;
+/// if there are none, the delta is the identity.
+pub fn trim_trailing_whitespace(text: &str) -> RopeDelta {
+    let mut builder = DeltaBuilder::new(text.len());
+    let mut line_start = 0;
+    while line_start < text.len() {
+        let line_end = text[line_start..].find('\n').map_or(text.len(), |i| line_start + i + 1);
+        let line = &text[line_start..line_end];
+        let content = line.trim_end_matches(is_line_ending);
+        let trimmed = content.trim_end_matches(|c| c == ' ' || c == '\t');
+        if trimmed.len() < content.len() {
+            let iv = Interval::new(line_start + trimmed.len(), line_start + content.len());
+            builder.delete(iv);
+        }
+        line_start = line_end;
+    }
+    builder.build()
+}
+
+/// Computes a delta that makes `text` end with exactly one `line_ending`
+/// if `ensure` is set, or with no line ending otherwise. Empty documents
+/// are left empty. If `text` already conforms, the delta is the identity.
+pub fn ensure_final_newline(text: &str, line_ending: &str, ensure: bool) -> RopeDelta {
+    let mut builder = DeltaBuilder::new(text.len());
+    let content_end = text.trim_end_matches(is_line_ending).len();
+    let tail = &text[content_end..];
+    if !ensure {
+        if !tail.is_empty() {
+            builder.delete(Interval::new(content_end, text.len()));
+        }
+    } else if !text.is_empty() && tail != line_ending {
+        if tail.starts_with(line_ending) {
+            builder.delete(Interval::new(content_end + line_ending.len(), text.len()));
+        } else {
+            builder.replace(Interval::new(content_end, text.len()), Rope::from(line_ending));
+        }
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(doc: &str, delta: &RopeDelta) -> String {
+        String::from(delta.apply(&Rope::from(doc)))
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let doc = "fn main() {  \n    foo();\n\t\n}\t \r\nbar";
+        let delta = trim_trailing_whitespace(doc);
+        assert_eq!(apply(doc, &delta), "fn main() {\n    foo();\n\n}\r\nbar");
+        // only the offending lines are touched
+        assert_eq!(delta.summary(), (Interval::new(11, 30), 14));
+        assert!(trim_trailing_whitespace("a\n  b\n").is_identity());
+    }
+
+    #[test]
+    fn final_newline() {
+        assert_eq!(apply("a", &ensure_final_newline("a", "\n", true)), "a\n");
+        assert!(ensure_final_newline("a\n", "\n", true).is_identity());
+        let delta = ensure_final_newline("a\n\n\n", "\n", true);
+        assert!(delta.is_simple_delete());
+        assert_eq!(apply("a\n\n\n", &delta), "a\n");
+        assert_eq!(apply("a\r\n", &ensure_final_newline("a\r\n", "\n", true)), "a\n");
+        assert!(ensure_final_newline("", "\n", true).is_identity());
+
+        assert_eq!(apply("a\n\n", &ensure_final_newline("a\n\n", "\n", false)), "a");
+        assert!(ensure_final_newline("a", "\n", false).is_identity());
+    }
+}
@@ -70,6 +70,7 @@ fn main() -> Result<(), io::Error> {
         name,
         version,
         scope: PluginScope::Global,
+        priority: 0,
         exec_path,
         activations: vec![PluginActivation::Autorun],
         commands: vec![],